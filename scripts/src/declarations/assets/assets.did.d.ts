@@ -4,32 +4,47 @@ import type { ActorMethod } from '@dfinity/agent';
 export type ApiError = { 'NotFound' : string } |
   { 'Unauthorized' : string } |
   { 'AlreadyExists' : string } |
-  { 'CanisterFailed' : CanisterFailedError };
+  { 'CanisterFailed' : CanisterFailedError } |
+  { 'BadRequest' : string };
 export interface Asset {
   'id' : number,
   'updated_at' : bigint,
   'asset_type' : AssetType,
+  'hash' : [] | [Uint8Array | number[]],
   'name' : string,
   'size' : number,
+  'tags' : [] | [Array<string>],
   'mime_type' : string,
   'created_at' : bigint,
   'user_id' : Principal,
   'is_favorite' : boolean,
   'parent_id' : [] | [number],
   'settings' : Settings,
+  'deleted_at' : [] | [bigint],
   'chunks' : Array<Chunk>,
   'extension' : string,
 }
+export interface AssetPage {
+  'total' : number,
+  'assets' : Array<Asset>,
+  'next_cursor' : [] | [number],
+}
+export interface AssetSort { 'sort_by' : SortBy, 'order' : SortOrder }
+export interface AssetStoreState {
+  'trash_retention_days' : [] | [number],
+  'invite_id' : number,
+  'asset_id' : number,
+  'users_canister' : [] | [Principal],
+}
 export type AssetType = { 'NFT' : Nft } |
   { 'Folder' : null } |
   { 'File' : null };
-export interface AssetsStore {
-  'shared' : Array<[Principal, Uint32Array | number[]]>,
-  'assets' : Array<[number, Asset]>,
-  'user_assets' : Array<[Principal, Uint32Array | number[]]>,
-  'asset_invites' : Array<[Principal, Invite]>,
-  'asset_id' : number,
-  'shared_with' : Array<[[Principal, number], Array<SharedWith>]>,
+export interface AssetVersion {
+  'hash' : [] | [Uint8Array | number[]],
+  'size' : number,
+  'created_at' : bigint,
+  'version' : number,
+  'chunks' : Array<Chunk>,
 }
 export interface CanisterFailedError {
   'code' : RejectionCode,
@@ -37,18 +52,38 @@ export interface CanisterFailedError {
 }
 export interface Chunk {
   'id' : number,
+  'owner' : [] | [Principal],
+  'hash' : [] | [Uint8Array | number[]],
   'canister' : Principal,
   'index' : number,
 }
+export interface CopyAsset {
+  'id' : number,
+  'parent_id' : [] | [number],
+  'on_conflict' : [] | [NameConflict],
+}
 export interface EditAsset {
   'id' : number,
   'name' : [] | [string],
   'is_favorite' : [] | [boolean],
   'parent_id' : [] | [number],
   'extension' : [] | [string],
+  'on_conflict' : [] | [NameConflict],
+}
+export interface EditSettings {
+  'id' : number,
+  'url' : [] | [string],
+  'privacy' : [] | [Privacy],
+}
+export interface EditTags {
+  'tags' : Array<string>,
+  'asset_ids' : Uint32Array | number[],
 }
 export interface Invite {
+  'id' : number,
+  'permission' : Permission,
   'status' : InviteStatus,
+  'invited_username' : [] | [string],
   'invited_by_username' : [] | [string],
   'asset_id' : number,
   'invited_by_principal' : Principal,
@@ -56,12 +91,19 @@ export interface Invite {
 }
 export type InviteStatus = { 'Accepted' : null } |
   { 'Declined' : null } |
+  { 'Cancelled' : null } |
   { 'Pending' : null };
-export interface MoveAsset { 'id' : number, 'parent_id' : [] | [number] }
+export type NameConflict = { 'Fail' : null } |
+  { 'Rename' : null } |
+  { 'Replace' : null };
 export interface Nft { 'principal' : Principal, 'index' : number }
+export type Permission = { 'CoOwner' : null } |
+  { 'Viewer' : null } |
+  { 'Editor' : null };
 export interface PostAsset {
   'id' : [] | [number],
   'asset_type' : AssetType,
+  'hash' : [] | [Uint8Array | number[]],
   'name' : string,
   'size' : number,
   'mime_type' : string,
@@ -70,6 +112,13 @@ export interface PostAsset {
   'settings' : Settings,
   'chunks' : Array<Chunk>,
   'extension' : string,
+  'on_conflict' : [] | [NameConflict],
+}
+export interface PostInvite {
+  'permission' : Permission,
+  'principal' : Principal,
+  'asset_id' : number,
+  'expires_at' : [] | [bigint],
 }
 export type Privacy = { 'Private' : null } |
   { 'Public' : null };
@@ -82,23 +131,108 @@ export type RejectionCode = { 'NoError' : null } |
   { 'CanisterReject' : null };
 export type Result = { 'Ok' : Asset } |
   { 'Err' : ApiError };
-export type Result_1 = { 'Ok' : Uint32Array | number[] } |
+export type Result_1 = { 'Ok' : Array<Asset> } |
+  { 'Err' : ApiError };
+export type Result_10 = { 'Ok' : AssetPage } |
+  { 'Err' : ApiError };
+export type Result_11 = { 'Ok' : Tag } |
+  { 'Err' : ApiError };
+export type Result_12 = { 'Ok' : number } |
+  { 'Err' : ApiError };
+export type Result_13 = { 'Ok' : Principal } |
+  { 'Err' : ApiError };
+export type Result_2 = { 'Ok' : Invite } |
+  { 'Err' : ApiError };
+export type Result_3 = { 'Ok' : Uint32Array | number[] } |
+  { 'Err' : ApiError };
+export type Result_4 = { 'Ok' : Array<AssetVersion> } |
   { 'Err' : ApiError };
-export type Result_2 = { 'Ok' : Array<Asset> } |
+export type Result_5 = { 'Ok' : Array<Invite> } |
   { 'Err' : ApiError };
-export type Result_3 = { 'Ok' : AssetsStore } |
+export type Result_6 = { 'Ok' : Array<SharedAsset> } |
   { 'Err' : ApiError };
+export type Result_7 = { 'Ok' : Array<SharedWith> } |
+  { 'Err' : ApiError };
+export type Result_8 = { 'Ok' : AssetStoreState } |
+  { 'Err' : ApiError };
+export type Result_9 = { 'Ok' : Array<Tag> } |
+  { 'Err' : ApiError };
+export interface SearchAssets {
+  'tag' : [] | [string],
+  'max_size' : [] | [number],
+  'asset_type' : [] | [AssetType],
+  'updated_after' : [] | [bigint],
+  'mime_type' : [] | [string],
+  'query' : [] | [string],
+  'min_size' : [] | [number],
+  'is_favorite' : [] | [boolean],
+  'updated_before' : [] | [bigint],
+}
 export interface Settings { 'url' : [] | [string], 'privacy' : Privacy }
+export interface SharedAsset {
+  'permission' : Permission,
+  'asset' : Asset,
+  'owner_principal' : Principal,
+  'owner_username' : [] | [string],
+}
 export interface SharedWith {
+  'permission' : Permission,
   'principal' : Principal,
   'username' : [] | [string],
 }
+export type SortBy = { 'UpdatedAt' : null } |
+  { 'Name' : null } |
+  { 'Size' : null } |
+  { 'CreatedAt' : null };
+export type SortOrder = { 'Descending' : null } |
+  { 'Ascending' : null };
+export interface Tag {
+  'name' : string,
+  'color' : [] | [string],
+  'count' : number,
+}
 export interface _SERVICE {
+  'accept_invite' : ActorMethod<[number], Result>,
   'add_asset' : ActorMethod<[PostAsset], Result>,
-  'delete_assets' : ActorMethod<[Uint32Array | number[]], Result_1>,
+  'add_tags' : ActorMethod<[EditTags], Result_1>,
+  'copy_assets' : ActorMethod<[Array<CopyAsset>], Result_1>,
+  'decline_invite' : ActorMethod<[number], Result_2>,
+  'delete_assets' : ActorMethod<[Uint32Array | number[]], Result_3>,
   'edit_asset' : ActorMethod<[EditAsset], Result>,
-  'get_all_assets' : ActorMethod<[], Result_2>,
-  'get_state' : ActorMethod<[], Result_3>,
-  'get_user_assets' : ActorMethod<[], Result_2>,
-  'move_assets' : ActorMethod<[Array<MoveAsset>], Result_2>,
+  'edit_settings' : ActorMethod<[EditSettings], Result>,
+  'empty_trash' : ActorMethod<[], Result_3>,
+  'get_all_assets' : ActorMethod<[], Result_1>,
+  'get_asset_by_url' : ActorMethod<[string], Result>,
+  'get_asset_versions' : ActorMethod<[number], Result_4>,
+  'get_invites' : ActorMethod<[], Result_5>,
+  'get_public_asset' : ActorMethod<[number], Result>,
+  'get_shared_assets' : ActorMethod<[], Result_6>,
+  'get_shared_with' : ActorMethod<[number], Result_7>,
+  'get_state' : ActorMethod<[], Result_8>,
+  'get_tags' : ActorMethod<[], Result_9>,
+  'get_trash' : ActorMethod<[], Result_1>,
+  'get_user_assets' : ActorMethod<[], Result_1>,
+  'list_folder' : ActorMethod<
+    [
+      [] | [number],
+      [] | [string],
+      [] | [number],
+      [] | [number],
+      [] | [AssetSort],
+    ],
+    Result_10
+  >,
+  'move_assets' : ActorMethod<[Array<CopyAsset>], Result_1>,
+  'remove_tags' : ActorMethod<[EditTags], Result_1>,
+  'restore_asset_version' : ActorMethod<[number, number], Result>,
+  'restore_assets' : ActorMethod<[Uint32Array | number[]], Result_1>,
+  'revoke_access' : ActorMethod<[number, Principal], Result_7>,
+  'search_assets' : ActorMethod<
+    [SearchAssets, [] | [number], [] | [number], [] | [AssetSort]],
+    Result_10
+  >,
+  'send_invite' : ActorMethod<[PostInvite], Result_2>,
+  'set_tag_color' : ActorMethod<[string, [] | [string]], Result_11>,
+  'set_trash_retention' : ActorMethod<[number], Result_12>,
+  'set_users_canister' : ActorMethod<[Principal], Result_13>,
 }
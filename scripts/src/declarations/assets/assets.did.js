@@ -12,33 +12,26 @@ export const idlFactory = ({ IDL }) => {
   });
   const Chunk = IDL.Record({
     'id' : IDL.Nat32,
+    'owner' : IDL.Opt(IDL.Principal),
+    'hash' : IDL.Opt(IDL.Vec(IDL.Nat8)),
     'canister' : IDL.Principal,
     'index' : IDL.Nat32,
   });
-  const PostAsset = IDL.Record({
-    'id' : IDL.Opt(IDL.Nat32),
-    'asset_type' : AssetType,
-    'name' : IDL.Text,
-    'size' : IDL.Nat32,
-    'mime_type' : IDL.Text,
-    'user_id' : IDL.Principal,
-    'parent_id' : IDL.Opt(IDL.Nat32),
-    'settings' : Settings,
-    'chunks' : IDL.Vec(Chunk),
-    'extension' : IDL.Text,
-  });
   const Asset = IDL.Record({
     'id' : IDL.Nat32,
     'updated_at' : IDL.Nat64,
     'asset_type' : AssetType,
+    'hash' : IDL.Opt(IDL.Vec(IDL.Nat8)),
     'name' : IDL.Text,
     'size' : IDL.Nat32,
+    'tags' : IDL.Opt(IDL.Vec(IDL.Text)),
     'mime_type' : IDL.Text,
     'created_at' : IDL.Nat64,
     'user_id' : IDL.Principal,
     'is_favorite' : IDL.Bool,
     'parent_id' : IDL.Opt(IDL.Nat32),
     'settings' : Settings,
+    'deleted_at' : IDL.Opt(IDL.Nat64),
     'chunks' : IDL.Vec(Chunk),
     'extension' : IDL.Text,
   });
@@ -60,56 +53,206 @@ export const idlFactory = ({ IDL }) => {
     'Unauthorized' : IDL.Text,
     'AlreadyExists' : IDL.Text,
     'CanisterFailed' : CanisterFailedError,
+    'BadRequest' : IDL.Text,
   });
   const Result = IDL.Variant({ 'Ok' : Asset, 'Err' : ApiError });
-  const Result_1 = IDL.Variant({ 'Ok' : IDL.Vec(IDL.Nat32), 'Err' : ApiError });
-  const EditAsset = IDL.Record({
+  const NameConflict = IDL.Variant({
+    'Fail' : IDL.Null,
+    'Rename' : IDL.Null,
+    'Replace' : IDL.Null,
+  });
+  const PostAsset = IDL.Record({
+    'id' : IDL.Opt(IDL.Nat32),
+    'asset_type' : AssetType,
+    'hash' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'name' : IDL.Text,
+    'size' : IDL.Nat32,
+    'mime_type' : IDL.Text,
+    'user_id' : IDL.Principal,
+    'parent_id' : IDL.Opt(IDL.Nat32),
+    'settings' : Settings,
+    'chunks' : IDL.Vec(Chunk),
+    'extension' : IDL.Text,
+    'on_conflict' : IDL.Opt(NameConflict),
+  });
+  const EditTags = IDL.Record({
+    'tags' : IDL.Vec(IDL.Text),
+    'asset_ids' : IDL.Vec(IDL.Nat32),
+  });
+  const Result_1 = IDL.Variant({ 'Ok' : IDL.Vec(Asset), 'Err' : ApiError });
+  const CopyAsset = IDL.Record({
     'id' : IDL.Nat32,
-    'name' : IDL.Opt(IDL.Text),
-    'is_favorite' : IDL.Opt(IDL.Bool),
     'parent_id' : IDL.Opt(IDL.Nat32),
-    'extension' : IDL.Opt(IDL.Text),
+    'on_conflict' : IDL.Opt(NameConflict),
+  });
+  const Permission = IDL.Variant({
+    'CoOwner' : IDL.Null,
+    'Viewer' : IDL.Null,
+    'Editor' : IDL.Null,
   });
-  const Result_2 = IDL.Variant({ 'Ok' : IDL.Vec(Asset), 'Err' : ApiError });
   const InviteStatus = IDL.Variant({
     'Accepted' : IDL.Null,
     'Declined' : IDL.Null,
+    'Cancelled' : IDL.Null,
     'Pending' : IDL.Null,
   });
   const Invite = IDL.Record({
+    'id' : IDL.Nat32,
+    'permission' : Permission,
     'status' : InviteStatus,
+    'invited_username' : IDL.Opt(IDL.Text),
     'invited_by_username' : IDL.Opt(IDL.Text),
     'asset_id' : IDL.Nat32,
     'invited_by_principal' : IDL.Principal,
     'expires_at' : IDL.Opt(IDL.Nat64),
   });
+  const Result_2 = IDL.Variant({ 'Ok' : Invite, 'Err' : ApiError });
+  const Result_3 = IDL.Variant({ 'Ok' : IDL.Vec(IDL.Nat32), 'Err' : ApiError });
+  const EditAsset = IDL.Record({
+    'id' : IDL.Nat32,
+    'name' : IDL.Opt(IDL.Text),
+    'is_favorite' : IDL.Opt(IDL.Bool),
+    'parent_id' : IDL.Opt(IDL.Nat32),
+    'extension' : IDL.Opt(IDL.Text),
+    'on_conflict' : IDL.Opt(NameConflict),
+  });
+  const EditSettings = IDL.Record({
+    'id' : IDL.Nat32,
+    'url' : IDL.Opt(IDL.Text),
+    'privacy' : IDL.Opt(Privacy),
+  });
+  const AssetVersion = IDL.Record({
+    'hash' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'size' : IDL.Nat32,
+    'created_at' : IDL.Nat64,
+    'version' : IDL.Nat32,
+    'chunks' : IDL.Vec(Chunk),
+  });
+  const Result_4 = IDL.Variant({
+    'Ok' : IDL.Vec(AssetVersion),
+    'Err' : ApiError,
+  });
+  const Result_5 = IDL.Variant({ 'Ok' : IDL.Vec(Invite), 'Err' : ApiError });
+  const SharedAsset = IDL.Record({
+    'permission' : Permission,
+    'asset' : Asset,
+    'owner_principal' : IDL.Principal,
+    'owner_username' : IDL.Opt(IDL.Text),
+  });
+  const Result_6 = IDL.Variant({
+    'Ok' : IDL.Vec(SharedAsset),
+    'Err' : ApiError,
+  });
   const SharedWith = IDL.Record({
+    'permission' : Permission,
     'principal' : IDL.Principal,
     'username' : IDL.Opt(IDL.Text),
   });
-  const AssetsStore = IDL.Record({
-    'shared' : IDL.Vec(IDL.Tuple(IDL.Principal, IDL.Vec(IDL.Nat32))),
-    'assets' : IDL.Vec(IDL.Tuple(IDL.Nat32, Asset)),
-    'user_assets' : IDL.Vec(IDL.Tuple(IDL.Principal, IDL.Vec(IDL.Nat32))),
-    'asset_invites' : IDL.Vec(IDL.Tuple(IDL.Principal, Invite)),
+  const Result_7 = IDL.Variant({
+    'Ok' : IDL.Vec(SharedWith),
+    'Err' : ApiError,
+  });
+  const AssetStoreState = IDL.Record({
+    'trash_retention_days' : IDL.Opt(IDL.Nat32),
+    'invite_id' : IDL.Nat32,
     'asset_id' : IDL.Nat32,
-    'shared_with' : IDL.Vec(
-      IDL.Tuple(IDL.Tuple(IDL.Principal, IDL.Nat32), IDL.Vec(SharedWith))
-    ),
+    'users_canister' : IDL.Opt(IDL.Principal),
   });
-  const Result_3 = IDL.Variant({ 'Ok' : AssetsStore, 'Err' : ApiError });
-  const MoveAsset = IDL.Record({
-    'id' : IDL.Nat32,
-    'parent_id' : IDL.Opt(IDL.Nat32),
+  const Result_8 = IDL.Variant({ 'Ok' : AssetStoreState, 'Err' : ApiError });
+  const Tag = IDL.Record({
+    'name' : IDL.Text,
+    'color' : IDL.Opt(IDL.Text),
+    'count' : IDL.Nat32,
+  });
+  const Result_9 = IDL.Variant({ 'Ok' : IDL.Vec(Tag), 'Err' : ApiError });
+  const SortBy = IDL.Variant({
+    'UpdatedAt' : IDL.Null,
+    'Name' : IDL.Null,
+    'Size' : IDL.Null,
+    'CreatedAt' : IDL.Null,
+  });
+  const SortOrder = IDL.Variant({
+    'Descending' : IDL.Null,
+    'Ascending' : IDL.Null,
+  });
+  const AssetSort = IDL.Record({ 'sort_by' : SortBy, 'order' : SortOrder });
+  const AssetPage = IDL.Record({
+    'total' : IDL.Nat32,
+    'assets' : IDL.Vec(Asset),
+    'next_cursor' : IDL.Opt(IDL.Nat32),
+  });
+  const Result_10 = IDL.Variant({ 'Ok' : AssetPage, 'Err' : ApiError });
+  const SearchAssets = IDL.Record({
+    'tag' : IDL.Opt(IDL.Text),
+    'max_size' : IDL.Opt(IDL.Nat32),
+    'asset_type' : IDL.Opt(AssetType),
+    'updated_after' : IDL.Opt(IDL.Nat64),
+    'mime_type' : IDL.Opt(IDL.Text),
+    'query' : IDL.Opt(IDL.Text),
+    'min_size' : IDL.Opt(IDL.Nat32),
+    'is_favorite' : IDL.Opt(IDL.Bool),
+    'updated_before' : IDL.Opt(IDL.Nat64),
+  });
+  const PostInvite = IDL.Record({
+    'permission' : Permission,
+    'principal' : IDL.Principal,
+    'asset_id' : IDL.Nat32,
+    'expires_at' : IDL.Opt(IDL.Nat64),
   });
+  const Result_11 = IDL.Variant({ 'Ok' : Tag, 'Err' : ApiError });
+  const Result_12 = IDL.Variant({ 'Ok' : IDL.Nat32, 'Err' : ApiError });
+  const Result_13 = IDL.Variant({ 'Ok' : IDL.Principal, 'Err' : ApiError });
   return IDL.Service({
+    'accept_invite' : IDL.Func([IDL.Nat32], [Result], []),
     'add_asset' : IDL.Func([PostAsset], [Result], []),
-    'delete_assets' : IDL.Func([IDL.Vec(IDL.Nat32)], [Result_1], []),
+    'add_tags' : IDL.Func([EditTags], [Result_1], []),
+    'copy_assets' : IDL.Func([IDL.Vec(CopyAsset)], [Result_1], []),
+    'decline_invite' : IDL.Func([IDL.Nat32], [Result_2], []),
+    'delete_assets' : IDL.Func([IDL.Vec(IDL.Nat32)], [Result_3], []),
     'edit_asset' : IDL.Func([EditAsset], [Result], []),
-    'get_all_assets' : IDL.Func([], [Result_2], ['query']),
-    'get_state' : IDL.Func([], [Result_3], ['query']),
-    'get_user_assets' : IDL.Func([], [Result_2], ['query']),
-    'move_assets' : IDL.Func([IDL.Vec(MoveAsset)], [Result_2], []),
+    'edit_settings' : IDL.Func([EditSettings], [Result], []),
+    'empty_trash' : IDL.Func([], [Result_3], []),
+    'get_all_assets' : IDL.Func([], [Result_1], ['query']),
+    'get_asset_by_url' : IDL.Func([IDL.Text], [Result], ['query']),
+    'get_asset_versions' : IDL.Func([IDL.Nat32], [Result_4], ['query']),
+    'get_invites' : IDL.Func([], [Result_5], ['query']),
+    'get_public_asset' : IDL.Func([IDL.Nat32], [Result], ['query']),
+    'get_shared_assets' : IDL.Func([], [Result_6], ['query']),
+    'get_shared_with' : IDL.Func([IDL.Nat32], [Result_7], ['query']),
+    'get_state' : IDL.Func([], [Result_8], ['query']),
+    'get_tags' : IDL.Func([], [Result_9], ['query']),
+    'get_trash' : IDL.Func([], [Result_1], ['query']),
+    'get_user_assets' : IDL.Func([], [Result_1], ['query']),
+    'list_folder' : IDL.Func(
+        [
+          IDL.Opt(IDL.Nat32),
+          IDL.Opt(IDL.Text),
+          IDL.Opt(IDL.Nat32),
+          IDL.Opt(IDL.Nat32),
+          IDL.Opt(AssetSort),
+        ],
+        [Result_10],
+        ['query'],
+      ),
+    'move_assets' : IDL.Func([IDL.Vec(CopyAsset)], [Result_1], []),
+    'remove_tags' : IDL.Func([EditTags], [Result_1], []),
+    'restore_asset_version' : IDL.Func([IDL.Nat32, IDL.Nat32], [Result], []),
+    'restore_assets' : IDL.Func([IDL.Vec(IDL.Nat32)], [Result_1], []),
+    'revoke_access' : IDL.Func([IDL.Nat32, IDL.Principal], [Result_7], []),
+    'search_assets' : IDL.Func(
+        [
+          SearchAssets,
+          IDL.Opt(IDL.Nat32),
+          IDL.Opt(IDL.Nat32),
+          IDL.Opt(AssetSort),
+        ],
+        [Result_10],
+        ['query'],
+      ),
+    'send_invite' : IDL.Func([PostInvite], [Result_2], []),
+    'set_tag_color' : IDL.Func([IDL.Text, IDL.Opt(IDL.Text)], [Result_11], []),
+    'set_trash_retention' : IDL.Func([IDL.Nat32], [Result_12], []),
+    'set_users_canister' : IDL.Func([IDL.Principal], [Result_13], []),
   });
 };
 export const init = ({ IDL }) => { return []; };
@@ -4,13 +4,16 @@ import type { ActorMethod } from '@dfinity/agent';
 export type ApiError = { 'NotFound' : string } |
   { 'Unauthorized' : string } |
   { 'AlreadyExists' : string } |
-  { 'CanisterFailed' : CanisterFailedError };
+  { 'CanisterFailed' : CanisterFailedError } |
+  { 'BadRequest' : string };
 export interface CanisterFailedError {
   'code' : RejectionCode,
   'message' : string,
 }
 export interface Chunk {
   'id' : number,
+  'owner' : [] | [Principal],
+  'hash' : [] | [Uint8Array | number[]],
   'canister' : Principal,
   'index' : number,
 }
@@ -19,6 +22,25 @@ export interface ChunkStoreState {
   'chunk_id' : number,
   'chunks' : Array<[number, Principal]>,
 }
+export interface HttpAsset {
+  'owner' : Principal,
+  'name' : string,
+  'mime_type' : string,
+  'chunks' : Array<Chunk>,
+  'asset_id' : number,
+}
+export interface HttpRequest {
+  'url' : string,
+  'method' : string,
+  'body' : Uint8Array | number[],
+  'headers' : Array<[string, string]>,
+}
+export interface HttpResponse {
+  'body' : Uint8Array | number[],
+  'headers' : Array<[string, string]>,
+  'streaming_strategy' : [] | [StreamingStrategy],
+  'status_code' : number,
+}
 export interface PostChunk { 'blob' : Uint8Array | number[], 'index' : number }
 export type RejectionCode = { 'NoError' : null } |
   { 'CanisterError' : null } |
@@ -27,26 +49,93 @@ export type RejectionCode = { 'NoError' : null } |
   { 'Unknown' : null } |
   { 'SysFatal' : null } |
   { 'CanisterReject' : null };
-export type Result = { 'Ok' : Chunk } |
+export type Result = { 'Ok' : number } |
+  { 'Err' : ApiError };
+export type Result_1 = { 'Ok' : Chunk } |
+  { 'Err' : ApiError };
+export type Result_10 = { 'Ok' : HttpAsset } |
   { 'Err' : ApiError };
-export type Result_1 = { 'Ok' : Uint32Array | number[] } |
+export type Result_2 = { 'Ok' : UploadSession } |
   { 'Err' : ApiError };
-export type Result_2 = {
+export type Result_3 = { 'Ok' : Array<Chunk> } |
+  { 'Err' : ApiError };
+export type Result_4 = { 'Ok' : Uint32Array | number[] } |
+  { 'Err' : ApiError };
+export type Result_5 = {
     'Ok' : Array<[[number, Principal], Uint8Array | number[]]>
   } |
   { 'Err' : ApiError };
-export type Result_3 = { 'Ok' : Uint8Array | number[] } |
+export type Result_6 = { 'Ok' : Array<Uint8Array | number[]> } |
+  { 'Err' : ApiError };
+export type Result_7 = { 'Ok' : Uint8Array | number[] } |
   { 'Err' : ApiError };
-export type Result_4 = { 'Ok' : ChunkStoreState } |
+export type Result_8 = { 'Ok' : ChunkStoreState } |
   { 'Err' : ApiError };
+export type Result_9 = { 'Ok' : UploadStatus } |
+  { 'Err' : ApiError };
+export interface StreamingCallbackHttpResponse {
+  'token' : [] | [StreamingCallbackToken],
+  'body' : Uint8Array | number[],
+}
+export interface StreamingCallbackToken {
+  'index' : number,
+  'asset_id' : number,
+}
+export type StreamingStrategy = {
+    'Callback' : {
+      'token' : StreamingCallbackToken,
+      'callback' : [Principal, string],
+    }
+  };
+export interface UploadSession {
+  'id' : number,
+  'total_size' : bigint,
+  'chunk_count' : number,
+  'chunks' : Array<[number, Chunk]>,
+  'expires_at' : bigint,
+  'received_size' : bigint,
+}
+export interface UploadStatus {
+  'session_id' : number,
+  'total_size' : bigint,
+  'chunk_count' : number,
+  'chunks' : Array<UploadedChunk>,
+  'expires_at' : bigint,
+  'received_size' : bigint,
+}
+export interface UploadedChunk {
+  'hash' : [] | [Uint8Array | number[]],
+  'size' : bigint,
+  'chunk_id' : number,
+  'index' : number,
+}
 export interface _SERVICE {
-  'add_chunk' : ActorMethod<[PostChunk], Result>,
-  'delete_chunks' : ActorMethod<[Uint32Array | number[]], Result_1>,
+  'abort_upload' : ActorMethod<[number], Result>,
+  'add_chunk' : ActorMethod<[PostChunk], Result_1>,
+  'add_chunk_intercanister_call' : ActorMethod<[PostChunk], Result_1>,
+  'begin_upload' : ActorMethod<[bigint, number], Result_2>,
+  'commit_upload' : ActorMethod<[number, Uint8Array | number[]], Result_3>,
+  'copy_chunks_intercanister_call' : ActorMethod<[Array<Chunk>], Result_3>,
+  'delete_chunks' : ActorMethod<[Uint32Array | number[]], Result_4>,
   'delete_chunks_intercanister_call' : ActorMethod<
     [Principal, Uint32Array | number[]],
-    Result_1
+    Result_4
+  >,
+  'get_all_chunks' : ActorMethod<[], Result_5>,
+  'get_chunk_hashes_intercanister_call' : ActorMethod<
+    [Uint32Array | number[]],
+    Result_6
+  >,
+  'get_chunk_intercanister_call' : ActorMethod<[number], Result_7>,
+  'get_chunks_by_chunk_id' : ActorMethod<[number], Result_7>,
+  'get_state' : ActorMethod<[], Result_8>,
+  'get_upload_status' : ActorMethod<[number], Result_9>,
+  'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
+  'http_request_streaming_callback' : ActorMethod<
+    [StreamingCallbackToken],
+    StreamingCallbackHttpResponse
   >,
-  'get_all_chunks' : ActorMethod<[], Result_2>,
-  'get_chunks_by_chunk_id' : ActorMethod<[number], Result_3>,
-  'get_state' : ActorMethod<[], Result_4>,
+  'put_chunk' : ActorMethod<[number, number, Uint8Array | number[]], Result_1>,
+  'remove_http_asset' : ActorMethod<[number], Result>,
+  'set_http_asset' : ActorMethod<[HttpAsset], Result_10>,
 }
@@ -1,13 +1,4 @@
 export const idlFactory = ({ IDL }) => {
-  const PostChunk = IDL.Record({
-    'blob' : IDL.Vec(IDL.Nat8),
-    'index' : IDL.Nat32,
-  });
-  const Chunk = IDL.Record({
-    'id' : IDL.Nat32,
-    'canister' : IDL.Principal,
-    'index' : IDL.Nat32,
-  });
   const RejectionCode = IDL.Variant({
     'NoError' : IDL.Null,
     'CanisterError' : IDL.Null,
@@ -26,33 +17,146 @@ export const idlFactory = ({ IDL }) => {
     'Unauthorized' : IDL.Text,
     'AlreadyExists' : IDL.Text,
     'CanisterFailed' : CanisterFailedError,
+    'BadRequest' : IDL.Text,
+  });
+  const Result = IDL.Variant({ 'Ok' : IDL.Nat32, 'Err' : ApiError });
+  const PostChunk = IDL.Record({
+    'blob' : IDL.Vec(IDL.Nat8),
+    'index' : IDL.Nat32,
+  });
+  const Chunk = IDL.Record({
+    'id' : IDL.Nat32,
+    'owner' : IDL.Opt(IDL.Principal),
+    'hash' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'canister' : IDL.Principal,
+    'index' : IDL.Nat32,
+  });
+  const Result_1 = IDL.Variant({ 'Ok' : Chunk, 'Err' : ApiError });
+  const UploadSession = IDL.Record({
+    'id' : IDL.Nat32,
+    'total_size' : IDL.Nat64,
+    'chunk_count' : IDL.Nat32,
+    'chunks' : IDL.Vec(IDL.Tuple(IDL.Nat32, Chunk)),
+    'expires_at' : IDL.Nat64,
+    'received_size' : IDL.Nat64,
   });
-  const Result = IDL.Variant({ 'Ok' : Chunk, 'Err' : ApiError });
-  const Result_1 = IDL.Variant({ 'Ok' : IDL.Vec(IDL.Nat32), 'Err' : ApiError });
-  const Result_2 = IDL.Variant({
+  const Result_2 = IDL.Variant({ 'Ok' : UploadSession, 'Err' : ApiError });
+  const Result_3 = IDL.Variant({ 'Ok' : IDL.Vec(Chunk), 'Err' : ApiError });
+  const Result_4 = IDL.Variant({ 'Ok' : IDL.Vec(IDL.Nat32), 'Err' : ApiError });
+  const Result_5 = IDL.Variant({
     'Ok' : IDL.Vec(
       IDL.Tuple(IDL.Tuple(IDL.Nat32, IDL.Principal), IDL.Vec(IDL.Nat8))
     ),
     'Err' : ApiError,
   });
-  const Result_3 = IDL.Variant({ 'Ok' : IDL.Vec(IDL.Nat8), 'Err' : ApiError });
+  const Result_6 = IDL.Variant({
+    'Ok' : IDL.Vec(IDL.Vec(IDL.Nat8)),
+    'Err' : ApiError,
+  });
+  const Result_7 = IDL.Variant({ 'Ok' : IDL.Vec(IDL.Nat8), 'Err' : ApiError });
   const ChunkStoreState = IDL.Record({
     'canister_owner' : IDL.Principal,
     'chunk_id' : IDL.Nat32,
     'chunks' : IDL.Vec(IDL.Tuple(IDL.Nat32, IDL.Principal)),
   });
-  const Result_4 = IDL.Variant({ 'Ok' : ChunkStoreState, 'Err' : ApiError });
+  const Result_8 = IDL.Variant({ 'Ok' : ChunkStoreState, 'Err' : ApiError });
+  const UploadedChunk = IDL.Record({
+    'hash' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'size' : IDL.Nat64,
+    'chunk_id' : IDL.Nat32,
+    'index' : IDL.Nat32,
+  });
+  const UploadStatus = IDL.Record({
+    'session_id' : IDL.Nat32,
+    'total_size' : IDL.Nat64,
+    'chunk_count' : IDL.Nat32,
+    'chunks' : IDL.Vec(UploadedChunk),
+    'expires_at' : IDL.Nat64,
+    'received_size' : IDL.Nat64,
+  });
+  const Result_9 = IDL.Variant({ 'Ok' : UploadStatus, 'Err' : ApiError });
+  const HttpRequest = IDL.Record({
+    'url' : IDL.Text,
+    'method' : IDL.Text,
+    'body' : IDL.Vec(IDL.Nat8),
+    'headers' : IDL.Vec(IDL.Tuple(IDL.Text, IDL.Text)),
+  });
+  const StreamingCallbackToken = IDL.Record({
+    'index' : IDL.Nat32,
+    'asset_id' : IDL.Nat32,
+  });
+  const StreamingCallbackHttpResponse = IDL.Record({
+    'token' : IDL.Opt(StreamingCallbackToken),
+    'body' : IDL.Vec(IDL.Nat8),
+  });
+  const StreamingStrategy = IDL.Variant({
+    'Callback' : IDL.Record({
+      'token' : StreamingCallbackToken,
+      'callback' : IDL.Func(
+          [StreamingCallbackToken],
+          [StreamingCallbackHttpResponse],
+          ['query'],
+        ),
+    }),
+  });
+  const HttpResponse = IDL.Record({
+    'body' : IDL.Vec(IDL.Nat8),
+    'headers' : IDL.Vec(IDL.Tuple(IDL.Text, IDL.Text)),
+    'streaming_strategy' : IDL.Opt(StreamingStrategy),
+    'status_code' : IDL.Nat16,
+  });
+  const HttpAsset = IDL.Record({
+    'owner' : IDL.Principal,
+    'name' : IDL.Text,
+    'mime_type' : IDL.Text,
+    'chunks' : IDL.Vec(Chunk),
+    'asset_id' : IDL.Nat32,
+  });
+  const Result_10 = IDL.Variant({ 'Ok' : HttpAsset, 'Err' : ApiError });
   return IDL.Service({
-    'add_chunk' : IDL.Func([PostChunk], [Result], []),
-    'delete_chunks' : IDL.Func([IDL.Vec(IDL.Nat32)], [Result_1], []),
+    'abort_upload' : IDL.Func([IDL.Nat32], [Result], []),
+    'add_chunk' : IDL.Func([PostChunk], [Result_1], []),
+    'add_chunk_intercanister_call' : IDL.Func([PostChunk], [Result_1], []),
+    'begin_upload' : IDL.Func([IDL.Nat64, IDL.Nat32], [Result_2], []),
+    'commit_upload' : IDL.Func([IDL.Nat32, IDL.Vec(IDL.Nat8)], [Result_3], []),
+    'copy_chunks_intercanister_call' : IDL.Func(
+        [IDL.Vec(Chunk)],
+        [Result_3],
+        [],
+      ),
+    'delete_chunks' : IDL.Func([IDL.Vec(IDL.Nat32)], [Result_4], []),
     'delete_chunks_intercanister_call' : IDL.Func(
         [IDL.Principal, IDL.Vec(IDL.Nat32)],
+        [Result_4],
+        [],
+      ),
+    'get_all_chunks' : IDL.Func([], [Result_5], ['query']),
+    'get_chunk_hashes_intercanister_call' : IDL.Func(
+        [IDL.Vec(IDL.Nat32)],
+        [Result_6],
+        ['query'],
+      ),
+    'get_chunk_intercanister_call' : IDL.Func(
+        [IDL.Nat32],
+        [Result_7],
+        ['query'],
+      ),
+    'get_chunks_by_chunk_id' : IDL.Func([IDL.Nat32], [Result_7], ['query']),
+    'get_state' : IDL.Func([], [Result_8], ['query']),
+    'get_upload_status' : IDL.Func([IDL.Nat32], [Result_9], ['query']),
+    'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
+    'http_request_streaming_callback' : IDL.Func(
+        [StreamingCallbackToken],
+        [StreamingCallbackHttpResponse],
+        ['query'],
+      ),
+    'put_chunk' : IDL.Func(
+        [IDL.Nat32, IDL.Nat32, IDL.Vec(IDL.Nat8)],
         [Result_1],
         [],
       ),
-    'get_all_chunks' : IDL.Func([], [Result_2], ['query']),
-    'get_chunks_by_chunk_id' : IDL.Func([IDL.Nat32], [Result_3], ['query']),
-    'get_state' : IDL.Func([], [Result_4], ['query']),
+    'remove_http_asset' : IDL.Func([IDL.Nat32], [Result], []),
+    'set_http_asset' : IDL.Func([HttpAsset], [Result_10], []),
   });
 };
 export const init = ({ IDL }) => { return [IDL.Opt(IDL.Principal)]; };
@@ -4,7 +4,8 @@ import type { ActorMethod } from '@dfinity/agent';
 export type ApiError = { 'NotFound' : string } |
   { 'Unauthorized' : string } |
   { 'AlreadyExists' : string } |
-  { 'CanisterFailed' : CanisterFailedError };
+  { 'CanisterFailed' : CanisterFailedError } |
+  { 'BadRequest' : string };
 export interface CanisterFailedError {
   'code' : RejectionCode,
   'message' : string,
@@ -22,13 +23,18 @@ export type Result_1 = { 'Ok' : Array<[Principal, Array<Principal>]> } |
   { 'Err' : ApiError };
 export type Result_2 = { 'Ok' : Array<User> } |
   { 'Err' : ApiError };
-export type Result_3 = { 'Ok' : Array<[Principal, User]> } |
+export type Result_3 = { 'Ok' : UserStoreState } |
   { 'Err' : ApiError };
 export interface User {
   'username' : [] | [string],
   'created_at' : bigint,
   'user_id' : Principal,
   'canisters' : Array<Principal>,
+  'alias_user_ids' : [] | [Array<Principal>],
+}
+export interface UserStoreState {
+  'chunks_wasm_size' : bigint,
+  'users' : Array<Principal>,
 }
 export interface _SERVICE {
   'create_user' : ActorMethod<[[] | [string]], Result>,
@@ -37,4 +43,5 @@ export interface _SERVICE {
   'get_chunks_wasm' : ActorMethod<[], Uint8Array | number[]>,
   'get_state' : ActorMethod<[], Result_3>,
   'get_user' : ActorMethod<[], Result>,
+  'get_user_intercanister_call' : ActorMethod<[Principal], Result>,
 }
@@ -4,6 +4,7 @@ export const idlFactory = ({ IDL }) => {
     'created_at' : IDL.Nat64,
     'user_id' : IDL.Principal,
     'canisters' : IDL.Vec(IDL.Principal),
+    'alias_user_ids' : IDL.Opt(IDL.Vec(IDL.Principal)),
   });
   const RejectionCode = IDL.Variant({
     'NoError' : IDL.Null,
@@ -23,6 +24,7 @@ export const idlFactory = ({ IDL }) => {
     'Unauthorized' : IDL.Text,
     'AlreadyExists' : IDL.Text,
     'CanisterFailed' : CanisterFailedError,
+    'BadRequest' : IDL.Text,
   });
   const Result = IDL.Variant({ 'Ok' : User, 'Err' : ApiError });
   const Result_1 = IDL.Variant({
@@ -30,10 +32,11 @@ export const idlFactory = ({ IDL }) => {
     'Err' : ApiError,
   });
   const Result_2 = IDL.Variant({ 'Ok' : IDL.Vec(User), 'Err' : ApiError });
-  const Result_3 = IDL.Variant({
-    'Ok' : IDL.Vec(IDL.Tuple(IDL.Principal, User)),
-    'Err' : ApiError,
+  const UserStoreState = IDL.Record({
+    'chunks_wasm_size' : IDL.Nat64,
+    'users' : IDL.Vec(IDL.Principal),
   });
+  const Result_3 = IDL.Variant({ 'Ok' : UserStoreState, 'Err' : ApiError });
   return IDL.Service({
     'create_user' : IDL.Func([IDL.Opt(IDL.Text)], [Result], []),
     'get_all_chunk_canisters' : IDL.Func([], [Result_1], ['query']),
@@ -41,6 +44,11 @@ export const idlFactory = ({ IDL }) => {
     'get_chunks_wasm' : IDL.Func([], [IDL.Vec(IDL.Nat8)], ['query']),
     'get_state' : IDL.Func([], [Result_3], ['query']),
     'get_user' : IDL.Func([], [Result], ['query']),
+    'get_user_intercanister_call' : IDL.Func(
+        [IDL.Principal],
+        [Result],
+        ['query'],
+      ),
   });
 };
 export const init = ({ IDL }) => { return []; };
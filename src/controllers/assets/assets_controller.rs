@@ -1,32 +1,67 @@
-use crate::assets_store::{ AssetsStore, STATE };
-use candid::candid_method;
+use crate::{ assets_store::{ AssetsStore, STATE }, legacy_store::LegacyAssetsStore };
+use candid::{ candid_method, Principal };
 use ic_cdk::{ caller, storage };
-use ic_cdk_macros::{ post_upgrade, pre_upgrade, query, update };
+use ic_cdk_macros::{ init, post_upgrade, pre_upgrade, query, update };
 use lib::{
-	types::{ api_error::ApiError, asset::{ Asset, PostAsset, EditAsset, MoveAsset } },
+	stable_memory,
+	types::{
+		api_error::ApiError,
+		asset::{
+			Asset,
+			AssetPage,
+			AssetSort,
+			AssetStoreState,
+			AssetVersion,
+			CopyAsset,
+			PostAsset,
+			EditAsset,
+			MoveAsset,
+			SearchAssets,
+			SharedAsset,
+			SharedWith,
+		},
+		invite::{ Invite, PostInvite },
+		settings::EditSettings,
+		tag::{ EditTags, Tag },
+	},
 	utils::{ validate_anonymous, validate_admin },
 };
 
+#[init]
+fn init() {
+	AssetsStore::start_trash_purge_timer();
+}
+
 #[pre_upgrade]
 fn pre_upgrade() {
-	STATE.with(|state| storage::stable_save((state,)).unwrap());
+	// The assets and their indexes are already in stable memory, only the counters and settings are saved
+	stable_memory::save_state(&AssetsStore::get_state());
 }
 
 #[post_upgrade]
 fn post_upgrade() {
-	let (old_store,): (AssetsStore,) = storage::stable_restore().unwrap();
+	let old_store = if stable_memory::is_memory_managed() {
+		AssetsStore::from(stable_memory::restore_state::<AssetStoreState>().unwrap())
+	} else {
+		// Canisters deployed before the assets were kept in stable memory hold the legacy layout
+		let (legacy_store,): (LegacyAssetsStore,) = storage::stable_restore().unwrap();
+		AssetsStore::from(legacy_store)
+	};
+
 	STATE.with(|state| {
 		*state.borrow_mut() = old_store;
 	});
+
+	AssetsStore::start_trash_purge_timer();
 }
 
 // ========== Admin calls
 
 #[query]
 #[candid_method(query)]
-fn get_state() -> Result<AssetsStore, ApiError> {
+fn get_state() -> Result<AssetStoreState, ApiError> {
 	match validate_admin(&caller()) {
-		Ok(_) => Ok(STATE.with(|state| state.borrow().clone())),
+		Ok(_) => Ok(AssetsStore::get_state()),
 		Err(err) => Err(err),
 	}
 }
@@ -40,6 +75,24 @@ fn get_all_assets() -> Result<Vec<Asset>, ApiError> {
 	}
 }
 
+#[update]
+#[candid_method(update)]
+fn set_trash_retention(days: u32) -> Result<u32, ApiError> {
+	match validate_admin(&caller()) {
+		Ok(_) => Ok(AssetsStore::set_trash_retention(days)),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn set_users_canister(users_canister: Principal) -> Result<Principal, ApiError> {
+	match validate_admin(&caller()) {
+		Ok(_) => Ok(AssetsStore::set_users_canister(users_canister)),
+		Err(err) => Err(err),
+	}
+}
+
 // ========== Non-admin calls
 
 #[query]
@@ -51,38 +104,241 @@ fn get_user_assets() -> Result<Vec<Asset>, ApiError> {
 	}
 }
 
+#[query]
+#[candid_method(query)]
+fn list_folder(
+	parent_id: Option<u32>,
+	tag: Option<String>,
+	cursor: Option<u32>,
+	limit: Option<u32>,
+	sort: Option<AssetSort>
+) -> Result<AssetPage, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::list_folder(caller_principal, parent_id, tag, cursor, limit, sort),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn search_assets(
+	search: SearchAssets,
+	cursor: Option<u32>,
+	limit: Option<u32>,
+	sort: Option<AssetSort>
+) -> Result<AssetPage, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => Ok(AssetsStore::search_assets(caller_principal, search, cursor, limit, sort)),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_shared_assets() -> Result<Vec<SharedAsset>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => Ok(AssetsStore::get_shared_assets(caller_principal)),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_public_asset(asset_id: u32) -> Result<Asset, ApiError> {
+	AssetsStore::get_public_asset(asset_id)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_asset_by_url(url: String) -> Result<Asset, ApiError> {
+	AssetsStore::get_asset_by_url(caller(), url)
+}
+
 #[update]
 #[candid_method(update)]
 async fn add_asset(asset: PostAsset) -> Result<Asset, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => Ok(AssetsStore::add_asset(caller_principal, asset).await),
+		Ok(caller_principal) => AssetsStore::add_asset(caller_principal, asset).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_asset_versions(asset_id: u32) -> Result<Vec<AssetVersion>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::get_asset_versions(caller_principal, asset_id),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+async fn restore_asset_version(asset_id: u32, version: u32) -> Result<Asset, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::restore_asset_version(caller_principal, asset_id, version).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+async fn edit_asset(asset: EditAsset) -> Result<Asset, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::edit_asset(caller_principal, asset).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+async fn edit_settings(settings: EditSettings) -> Result<Asset, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::edit_settings(caller_principal, settings).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+async fn move_assets(assets: Vec<MoveAsset>) -> Result<Vec<Asset>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::move_assets(caller_principal, assets).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+async fn copy_assets(assets: Vec<CopyAsset>) -> Result<Vec<Asset>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::copy_assets(caller_principal, assets).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+async fn delete_assets(asset_ids: Vec<u32>) -> Result<Vec<u32>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::delete_assets(caller_principal, asset_ids).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_trash() -> Result<Vec<Asset>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => Ok(AssetsStore::get_trash(caller_principal)),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+async fn restore_assets(asset_ids: Vec<u32>) -> Result<Vec<Asset>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::restore_assets(caller_principal, asset_ids).await,
 		Err(err) => Err(err),
 	}
 }
 
 #[update]
 #[candid_method(update)]
-fn edit_asset(asset: EditAsset) -> Result<Asset, ApiError> {
+async fn empty_trash() -> Result<Vec<u32>, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => AssetsStore::edit_asset(caller_principal, asset),
+		Ok(caller_principal) => Ok(AssetsStore::empty_trash(caller_principal).await),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_tags() -> Result<Vec<Tag>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => Ok(AssetsStore::get_tags(caller_principal)),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn add_tags(edit_tags: EditTags) -> Result<Vec<Asset>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::add_tags(caller_principal, edit_tags),
 		Err(err) => Err(err),
 	}
 }
 
 #[update]
 #[candid_method(update)]
-fn move_assets(assets: Vec<MoveAsset>) -> Result<Vec<Asset>, ApiError> {
+fn remove_tags(edit_tags: EditTags) -> Result<Vec<Asset>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::remove_tags(caller_principal, edit_tags),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn set_tag_color(tag: String, color: Option<String>) -> Result<Tag, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::set_tag_color(caller_principal, tag, color),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_invites() -> Result<Vec<Invite>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => Ok(AssetsStore::get_invites(caller_principal)),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+async fn send_invite(invite: PostInvite) -> Result<Invite, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::send_invite(caller_principal, invite).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn accept_invite(invite_id: u32) -> Result<Asset, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::accept_invite(caller_principal, invite_id),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn decline_invite(invite_id: u32) -> Result<Invite, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AssetsStore::decline_invite(caller_principal, invite_id),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_shared_with(asset_id: u32) -> Result<Vec<SharedWith>, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => AssetsStore::move_assets(caller_principal, assets),
+		Ok(caller_principal) => AssetsStore::get_shared_with(caller_principal, asset_id),
 		Err(err) => Err(err),
 	}
 }
 
 #[update]
 #[candid_method(update)]
-fn delete_assets(asset_ids: Vec<u32>) -> Result<Vec<u32>, ApiError> {
+fn revoke_access(asset_id: u32, principal: Principal) -> Result<Vec<SharedWith>, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => AssetsStore::delete_assets(caller_principal, asset_ids),
+		Ok(caller_principal) => AssetsStore::revoke_access(caller_principal, asset_id, principal),
 		Err(err) => Err(err),
 	}
 }
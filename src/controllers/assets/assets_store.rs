@@ -1,27 +1,174 @@
-use candid::{ CandidType, Deserialize, Principal };
-use ic_cdk::api::{ time, call };
-use lib::types::{
-	api_error::ApiError,
-	asset::{ Asset, EditAsset, PostAsset, AssetType, MoveAsset, SharedWith },
-	invite::Invite,
+use candid::Principal;
+use ic_cdk::{ spawn, api::{ time, call::{ self, CallResult } }, timer::{ set_timer, set_timer_interval } };
+use ic_stable_structures::memory_manager::MemoryId;
+use lib::{
+	stable_memory::StableMap,
+	types::{
+		api_error::{ ApiError, CanisterFailedError },
+		asset::{
+			Asset,
+			AssetPage,
+			AssetSort,
+			AssetStoreState,
+			AssetType,
+			AssetVersion,
+			CopyAsset,
+			EditAsset,
+			MoveAsset,
+			NameConflict,
+			Permission,
+			PostAsset,
+			SearchAssets,
+			SharedAsset,
+			SharedWith,
+			SortBy,
+			SortOrder,
+		},
+		chunk::{ Chunk, PostChunk },
+		http::HttpAsset,
+		invite::{ Invite, InviteStatus, PostInvite },
+		settings::{ Privacy, EditSettings, Settings },
+		tag::{ EditTags, Tag },
+		user::User,
+	},
+	utils::hash_file,
 };
-use std::{ cell::RefCell, collections::{ HashMap, HashSet } };
+use std::{
+	cell::RefCell,
+	cmp::Ordering,
+	collections::{ BTreeMap, BTreeSet, HashMap, HashSet, VecDeque },
+	mem::discriminant,
+	time::Duration,
+};
+
+// Days deleted assets are kept in the trash when no retention is configured
+const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+// Time between two purges of the trash
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+// Maximum number of assets removed by one purge of the trash, including the children of folders. The rest is
+// removed by the next purges
+const TRASH_PURGE_BATCH_SIZE: usize = 100;
+// Maximum number of failed deletions of chunks and of HTTP assets retried by one purge of the trash
+const FAILED_DELETION_RETRY_BATCH_SIZE: usize = 500;
+// Maximum number of previous versions that are kept per asset
+const MAX_ASSET_VERSIONS: usize = 10;
+// Number of assets returned per page when no limit is given
+const DEFAULT_PAGE_LIMIT: u32 = 100;
+// Maximum number of assets returned per page
+const MAX_PAGE_LIMIT: u32 = 500;
+// Maximum number of tags per asset
+const MAX_ASSET_TAGS: usize = 20;
+// Maximum number of characters of a tag
+const MAX_TAG_LENGTH: usize = 32;
+// Maximum number of characters of a URL slug
+pub const MAX_URL_LENGTH: usize = 128;
+// Maximum number of characters of a word in the search index. Longer words are cut, so they fit in a key
+const MAX_TOKEN_LENGTH: usize = 32;
 
-#[derive(CandidType, Clone, Deserialize, Default)]
+// Memories the stable maps of the store are kept in. Memory 0 holds the heap state saved on upgrades
+const ASSETS_MEMORY_ID: MemoryId = MemoryId::new(1);
+const USER_ASSETS_MEMORY_ID: MemoryId = MemoryId::new(2);
+const FOLDER_ASSETS_MEMORY_ID: MemoryId = MemoryId::new(3);
+const ASSET_INVITES_MEMORY_ID: MemoryId = MemoryId::new(4);
+const ASSET_INVITE_IDS_MEMORY_ID: MemoryId = MemoryId::new(5);
+const SHARED_MEMORY_ID: MemoryId = MemoryId::new(6);
+const SHARED_WITH_MEMORY_ID: MemoryId = MemoryId::new(7);
+const URLS_MEMORY_ID: MemoryId = MemoryId::new(8);
+const VERSIONS_MEMORY_ID: MemoryId = MemoryId::new(9);
+const ASSET_TOKENS_MEMORY_ID: MemoryId = MemoryId::new(10);
+const TAG_COLORS_MEMORY_ID: MemoryId = MemoryId::new(11);
+const TRASH_MEMORY_ID: MemoryId = MemoryId::new(12);
+const FAILED_CHUNK_DELETIONS_MEMORY_ID: MemoryId = MemoryId::new(13);
+const FAILED_HTTP_REMOVALS_MEMORY_ID: MemoryId = MemoryId::new(14);
+
+// The counters and settings are kept on the heap and saved on upgrades, everything else is kept in stable maps,
+// so upgrades don't depend on the number of assets
 pub struct AssetsStore {
 	// Increment of asset IDs
 	pub asset_id: u32,
-	// All assets
-	pub assets: HashMap<u32, Asset>,
-	// Caller's assets. Principal = caller, u32 = asset_id
-	pub user_assets: HashMap<Principal, Vec<u32>>,
-	// Asset invitations. User has invited you to shared his asset
+	// Increment of invite IDs
+	pub invite_id: u32,
+	// Days deleted assets are kept in the trash before they are purged
+	pub trash_retention_days: Option<u32>,
+	// Canister that stores the users and their chunks canisters
+	pub users_canister: Option<Principal>,
+	// All assets. u32 = asset_id
+	pub assets: StableMap<u32, Asset>,
+	// Index of the assets by owner. Principal = owner, u32 = asset_id
+	pub user_assets: StableMap<(Principal, u32), ()>,
+	// Index of the assets by folder. Principal = owner, Option<u32> = parent_id (None is the root), u32 = asset_id
+	pub folder_assets: StableMap<(Principal, Option<u32>, u32), ()>,
+	// Asset invitations. User has invited you to shared his asset. Principal = invited user, u32 = invite_id
 	// Example: User A sends an invite to User B to have acces to User A's asset
-	pub asset_invites: HashMap<Principal, Invite>,
-	// Shared assets that are not caller's assets, but is granted access to. Principal = caller, u32 = asset_id
-	pub shared: HashMap<Principal, Vec<u32>>,
+	pub asset_invites: StableMap<(Principal, u32), Invite>,
+	// Index of the invites by asset. u32 = asset_id, Principal = invited user, u32 = invite_id
+	pub asset_invite_ids: StableMap<(u32, Principal, u32), ()>,
+	// Index of the assets shared with a user that are not the user's assets. Principal = user, u32 = asset_id
+	pub shared: StableMap<(Principal, u32), ()>,
 	// List of people that have access to caller's assets. Principal = caller, u32 = asset_id
-	pub shared_with: HashMap<(Principal, u32), Vec<SharedWith>>,
+	pub shared_with: StableMap<(Principal, u32), Vec<SharedWith>>,
+	// URL slugs of assets. String = url, u32 = asset_id
+	pub urls: StableMap<String, u32>,
+	// Previous versions of re-uploaded files, oldest first. u32 = asset_id
+	pub versions: StableMap<u32, Vec<AssetVersion>>,
+	// Search index of the words in the names and extensions of assets. String = lowercase word, u32 = asset_id
+	pub asset_tokens: StableMap<(String, u32), ()>,
+	// Colour labels of the tags of a user. Principal = user, first String = tag, second String = colour
+	pub tag_colors: StableMap<(Principal, String), String>,
+	// Index of the assets in the trash by the time they were deleted. u64 = deleted_at, u32 = asset_id
+	pub trash: StableMap<(u64, u32), ()>,
+	// Chunks that couldn't be deleted from their chunks canister, retried by the purges of the trash. First Principal
+	// = chunks canister, second Principal = principal the blob is stored under, u32 = chunk_id
+	pub failed_chunk_deletions: StableMap<(Principal, Principal, u32), ()>,
+	// Assets that couldn't be removed from being served over HTTP, retried by the purges of the trash. Principal =
+	// chunks canister, u32 = asset_id
+	pub failed_http_removals: StableMap<(Principal, u32), ()>,
+}
+
+impl Default for AssetsStore {
+	fn default() -> Self {
+		Self::from(AssetStoreState::default())
+	}
+}
+
+impl From<AssetStoreState> for AssetsStore {
+	/// Restores the heap state and opens the stable maps, which keep their entries across upgrades.
+	fn from(state: AssetStoreState) -> Self {
+		Self {
+			asset_id: state.asset_id,
+			invite_id: state.invite_id,
+			trash_retention_days: state.trash_retention_days,
+			users_canister: state.users_canister,
+			assets: StableMap::init(ASSETS_MEMORY_ID),
+			user_assets: StableMap::init(USER_ASSETS_MEMORY_ID),
+			folder_assets: StableMap::init(FOLDER_ASSETS_MEMORY_ID),
+			asset_invites: StableMap::init(ASSET_INVITES_MEMORY_ID),
+			asset_invite_ids: StableMap::init(ASSET_INVITE_IDS_MEMORY_ID),
+			shared: StableMap::init(SHARED_MEMORY_ID),
+			shared_with: StableMap::init(SHARED_WITH_MEMORY_ID),
+			urls: StableMap::init(URLS_MEMORY_ID),
+			versions: StableMap::init(VERSIONS_MEMORY_ID),
+			asset_tokens: StableMap::init(ASSET_TOKENS_MEMORY_ID),
+			tag_colors: StableMap::init(TAG_COLORS_MEMORY_ID),
+			trash: StableMap::init(TRASH_MEMORY_ID),
+			failed_chunk_deletions: StableMap::init(FAILED_CHUNK_DELETIONS_MEMORY_ID),
+			failed_http_removals: StableMap::init(FAILED_HTTP_REMOVALS_MEMORY_ID),
+		}
+	}
+}
+
+// Key of a copied asset: index of the copied tree and ID of the original asset
+type CopyKey = (usize, u32);
+
+// Changes of a batch that are validated but not yet applied to the store
+#[derive(Default)]
+struct NameBatch {
+	// New parent IDs of moved assets. u32 = asset_id
+	moved_parent_ids: HashMap<u32, Option<u32>>,
+	// Names taken by assets of the batch. Principal = owner, Option<u32> = parent_id, Strings = lowercase name and
+	// extension, u32 = asset_id
+	claimed_names: HashMap<(Principal, Option<u32>, String, String), u32>,
 }
 
 thread_local! {
@@ -31,12 +178,115 @@ thread_local! {
 impl AssetsStore {
 	// ========== Admin calls
 
+	/// Get the part of the store that is kept on the heap.
+	///
+	/// # Returns
+	/// - `AssetStoreState` - Counters and settings of the store
+	pub fn get_state() -> AssetStoreState {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			AssetStoreState {
+				asset_id: state.asset_id,
+				invite_id: state.invite_id,
+				trash_retention_days: state.trash_retention_days,
+				users_canister: state.users_canister,
+			}
+		})
+	}
+
 	/// Get all assets.
 	///
 	/// # Returns
 	/// - `Vec<Asset>` - Assets
 	pub fn get_all_assets() -> Vec<Asset> {
-		STATE.with(|state| state.borrow().assets.values().cloned().collect())
+		STATE.with(|state| state.borrow().assets.values().collect())
+	}
+
+	/// Set the number of days deleted assets are kept in the trash.
+	///
+	/// # Arguments
+	/// - `days` - Retention in days
+	///
+	/// # Returns
+	/// - `u32` - Retention in days
+	pub fn set_trash_retention(days: u32) -> u32 {
+		STATE.with(|state| {
+			state.borrow_mut().trash_retention_days = Some(days);
+
+			days
+		})
+	}
+
+	/// Set the canister that stores the users. It is asked for the chunks canisters of users.
+	///
+	/// # Arguments
+	/// - `users_canister` - Principal of the users canister
+	///
+	/// # Returns
+	/// - `Principal` - Principal of the users canister
+	pub fn set_users_canister(users_canister: Principal) -> Principal {
+		STATE.with(|state| {
+			state.borrow_mut().users_canister = Some(users_canister);
+
+			users_canister
+		})
+	}
+
+	/// Start the timer that purges the trash. Timers don't survive upgrades, so this is called on install and after
+	/// every upgrade.
+	///
+	/// # Returns
+	/// - `()` - No return value
+	pub fn start_trash_purge_timer() {
+		set_timer_interval(TRASH_PURGE_INTERVAL, || spawn(Self::purge_trash()));
+	}
+
+	/// Permanently delete assets that have been in the trash longer than the retention. Assets are removed in
+	/// batches, as long as a batch is full the next batch is removed right after it. Nested children of folders
+	/// count against the batch and are removed before the folders. Chunks and HTTP assets that couldn't be deleted
+	/// before are retried.
+	///
+	/// # Returns
+	/// - `()` - No return value
+	async fn purge_trash() {
+		let now = time();
+
+		let (purged_chunks, is_batch_full) = STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			let (purge_asset_ids, is_batch_full) = Self::find_purge_asset_ids(&state, now);
+			let (_, purged_chunks) = Self::remove_listed_assets(&mut state, &purge_asset_ids);
+
+			(purged_chunks, is_batch_full)
+		});
+
+		if is_batch_full {
+			set_timer(Duration::ZERO, || spawn(Self::purge_trash()));
+		}
+
+		let (failed_chunk_ids, failed_http_removals) = STATE.with(|state| {
+			let state = state.borrow();
+			let mut failed_chunk_ids: HashMap<(Principal, Principal), Vec<u32>> = HashMap::new();
+
+			for (canister, owner, chunk_id) in state.failed_chunk_deletions.keys().take(FAILED_DELETION_RETRY_BATCH_SIZE) {
+				failed_chunk_ids.entry((canister, owner)).or_default().push(chunk_id);
+			}
+
+			let failed_http_removals: Vec<(Principal, u32)> = state.failed_http_removals
+				.keys()
+				.take(FAILED_DELETION_RETRY_BATCH_SIZE)
+				.collect();
+
+			(failed_chunk_ids, failed_http_removals)
+		});
+
+		for (canister, asset_id) in failed_http_removals {
+			Self::remove_http_asset_id(canister, asset_id).await;
+		}
+
+		Self::delete_chunk_ids(failed_chunk_ids).await;
+		Self::delete_chunks(purged_chunks).await;
 	}
 
 	// ========== Non-admin calls
@@ -49,22 +299,203 @@ impl AssetsStore {
 	/// # Returns
 	/// - `Vec<Asset>` - Assets
 	pub fn get_user_assets(caller_principal: Principal) -> Vec<Asset> {
+		STATE.with(|state| {
+			Self::find_user_assets(&state.borrow(), &caller_principal)
+				.into_iter()
+				.filter(|asset| asset.deleted_at.is_none())
+				.collect()
+		})
+	}
+
+	/// Get the assets of the caller that are in the trash.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Asset>` - Deleted assets
+	pub fn get_trash(caller_principal: Principal) -> Vec<Asset> {
+		STATE.with(|state| {
+			Self::find_user_assets(&state.borrow(), &caller_principal)
+				.into_iter()
+				.filter(|asset| asset.deleted_at.is_some())
+				.collect()
+		})
+	}
+
+	/// List the assets in a folder, one page at a time. Assets in the trash are not listed.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `parent_id` - Folder ID, `None` for the root of the caller
+	/// - `tag` - Only list assets with this tag
+	/// - `cursor` - Cursor returned by the previous page, `None` for the first page
+	/// - `limit` - Maximum number of assets to return
+	/// - `sort` - Order of the assets, by name if not given
+	///
+	/// # Returns
+	/// - `AssetPage` - Assets, the total number of assets in the folder and the cursor of the next page
+	pub fn list_folder(
+		caller_principal: Principal,
+		parent_id: Option<u32>,
+		tag: Option<String>,
+		cursor: Option<u32>,
+		limit: Option<u32>,
+		sort: Option<AssetSort>
+	) -> Result<AssetPage, ApiError> {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			// The assets in a folder are owned by the owner of the folder
+			let owner_principal = match parent_id {
+				Some(parent_id) => {
+					let parent = Self::check_permission(&state, &caller_principal, parent_id, Permission::Viewer)?;
+
+					if parent.asset_type != AssetType::Folder {
+						return Err(ApiError::BadRequest("ASSET_NOT_A_FOLDER".to_string()));
+					}
+
+					parent.user_id
+				}
+				None => caller_principal,
+			};
+
+			let mut assets: Vec<Asset> = Self::find_folder_assets(&state, &owner_principal, parent_id)
+				.into_iter()
+				.filter(|asset| asset.deleted_at.is_none())
+				.filter(|asset| tag.as_ref().is_none_or(|tag| Self::has_tag(asset, tag)))
+				.collect();
+
+			Self::sort_assets(&mut assets, sort);
+
+			Ok(Self::paginate_assets(&assets, cursor, limit))
+		})
+	}
+
+	/// Search the assets of the caller and the assets shared with the caller. The query matches the start of the
+	/// words in the name and the extension of an asset. Assets in the trash are not searched.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `search` - Query and filters
+	/// - `cursor` - Cursor returned by the previous page, `None` for the first page
+	/// - `limit` - Maximum number of assets to return
+	/// - `sort` - Order of the assets, by name if not given
+	///
+	/// # Returns
+	/// - `AssetPage` - Matching assets, the total number of matches and the cursor of the next page
+	pub fn search_assets(
+		caller_principal: Principal,
+		search: SearchAssets,
+		cursor: Option<u32>,
+		limit: Option<u32>,
+		sort: Option<AssetSort>
+	) -> AssetPage {
 		STATE.with(|state| {
 			let state = state.borrow();
 
-			// Get user's assets
-			let user_asset_ids_by_principal = state.user_assets.get(&caller_principal).cloned().unwrap_or_default();
+			// Every word of the query has to match the start of a word of the asset, so the candidates come from the
+			// search index. Without a query all assets of the caller and the assets shared with the caller are candidates
+			let query_tokens = search.query.as_deref().map(Self::tokenize).unwrap_or_default();
+			let mut opt_candidate_ids: Option<HashSet<u32>> = None;
+
+			for query_token in query_tokens {
+				let matching_asset_ids = state.asset_tokens
+					.range_from(&(query_token.clone(), 0))
+					.map(|(key, _)| key)
+					.take_while(|(token, _)| token.starts_with(&query_token))
+					.map(|(_, asset_id)| asset_id)
+					.filter(|asset_id| opt_candidate_ids.as_ref().is_none_or(|candidate_ids| candidate_ids.contains(asset_id)))
+					.collect();
+
+				opt_candidate_ids = Some(matching_asset_ids);
+			}
+
+			let candidate_ids = opt_candidate_ids.unwrap_or_else(|| {
+				let mut accessible_asset_ids: HashSet<u32> = state.user_assets
+					.prefix_keys(&caller_principal)
+					.map(|(_, asset_id)| asset_id)
+					.collect();
+
+				accessible_asset_ids.extend(
+					Self::find_shared_assets(&state, &caller_principal)
+						.iter()
+						.map(|shared_asset| shared_asset.asset.id)
+				);
+
+				accessible_asset_ids
+			});
+
+			// Only assets the caller can view are returned, check_permission skips assets in the trash
+			let mut assets: Vec<Asset> = candidate_ids
+				.into_iter()
+				.filter_map(|asset_id| Self::check_permission(&state, &caller_principal, asset_id, Permission::Viewer).ok())
+				.filter(|asset| Self::matches_search(asset, &search))
+				.collect();
+
+			Self::sort_assets(&mut assets, sort);
+
+			Self::paginate_assets(&assets, cursor, limit)
+		})
+	}
+
+	/// Get assets that other users have shared with the caller.
+	/// If a shared asset is a folder, all of its children are returned as well.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<SharedAsset>` - Shared assets including the owner
+	pub fn get_shared_assets(caller_principal: Principal) -> Vec<SharedAsset> {
+		STATE.with(|state| Self::find_shared_assets(&state.borrow(), &caller_principal))
+	}
+
+	/// Get a public asset. This can be called by anyone, including anonymous users.
+	///
+	/// # Arguments
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `Asset` - Public asset including the locations of its chunks
+	pub fn get_public_asset(asset_id: u32) -> Result<Asset, ApiError> {
+		STATE.with(|state| {
+			let state = state.borrow();
 
-			// Loop through all assets and check if the asset_id contains in user's assets list
+			// Private assets are reported as not found to not leak their existence
 			state.assets
-				.values()
-				.filter(|asset| user_asset_ids_by_principal.contains(&asset.id))
-				.cloned()
-				.collect()
+				.get(&asset_id)
+				.filter(|asset| asset.deleted_at.is_none() && matches!(asset.settings.privacy, Privacy::Public))
+				.ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))
+		})
+	}
+
+	/// Get an asset by its URL slug. Private assets are only returned to users that have access to them.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `url` - URL slug of the asset
+	///
+	/// # Returns
+	/// - `Asset` - Asset
+	pub fn get_asset_by_url(caller_principal: Principal, url: String) -> Result<Asset, ApiError> {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			let asset_id = state.urls.get(&url).ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))?;
+
+			match state.assets.get(&asset_id).filter(|asset| asset.deleted_at.is_none()) {
+				Some(asset) if matches!(asset.settings.privacy, Privacy::Public) => Ok(asset),
+				Some(_) => Self::check_permission(&state, &caller_principal, asset_id, Permission::Viewer),
+				None => Err(ApiError::NotFound("ASSET_NOT_FOUND".to_string())),
+			}
 		})
 	}
 
 	/// Add asset.
+	/// Users with edit permission can add assets to a shared folder or overwrite a shared file. The added asset
+	/// will be owned by the owner of the shared folder. When a file replaces a file with the same name, it is
+	/// added as a new version of that file.
 	///
 	/// # Arguments
 	/// - `caller_principal` - Principal of the caller
@@ -72,236 +503,2964 @@ impl AssetsStore {
 	///
 	/// # Returns
 	/// - `Asset` - Added asset
-	pub async fn add_asset(caller_principal: Principal, post_asset: PostAsset) -> Asset {
-		// Delete previous chunks if asset is a file. Folder doesn't have chunks
-		if post_asset.asset_type == AssetType::File {
-			Self::delete_existing_chunks(&caller_principal, &post_asset).await;
+	pub async fn add_asset(caller_principal: Principal, mut post_asset: PostAsset) -> Result<Asset, ApiError> {
+		// The chunks belong to the caller, who has to store them in one of their own chunks canisters
+		for chunk in post_asset.chunks.iter_mut() {
+			chunk.owner = Some(caller_principal);
 		}
 
-		STATE.with(|state| {
+		Self::check_chunk_owners(&post_asset.chunks, caller_principal).await?;
+
+		// The hashes of the chunks are never taken from the client. They are only read from the chunks canisters to
+		// verify a declared hash, the file stays unverified without one
+		for chunk in post_asset.chunks.iter_mut() {
+			chunk.hash = None;
+		}
+
+		if post_asset.hash.is_some() && post_asset.asset_type == AssetType::File && !post_asset.chunks.is_empty() {
+			// Chunks canisters that can't return the hashes, e.g. ones that aren't upgraded yet, leave the file
+			// unverified instead of failing the upload
+			if let Ok(chunks) = Self::stored_chunk_hashes(post_asset.chunks.clone()).await {
+				// A declared hash has to match the hashes of the chunks, so corrupted or misordered chunks are rejected
+				if hash_file(&chunks) != post_asset.hash {
+					return Err(ApiError::BadRequest("ASSET_HASH_MISMATCH".to_string()));
+				}
+
+				post_asset.chunks = chunks;
+			}
+		}
+
+		// The access check and the swap of the chunks happen in one call, so the asset can't change in between
+		let (asset, pruned_chunks, trashed_assets) = STATE.with(|state| {
 			let mut state = state.borrow_mut();
 
-			// Find all user_assets linked to the principal (caller)
-			let user_asset_ids = state.user_assets.get(&caller_principal).cloned().unwrap_or_default();
-			// Find a specific asset with given value
-			let asset_id = user_asset_ids
-				.into_iter()
-				.find(|&asset_id| post_asset.id.filter(|id| *id == asset_id).is_some());
+			// Find an existing asset the caller is allowed to overwrite
+			let existing_asset_id = post_asset.id
+				.and_then(|asset_id| Self::check_permission(&state, &caller_principal, asset_id, Permission::Editor).ok())
+				.map(|existing_asset| existing_asset.id);
 
-			// TODO: loop through principals and add invite to 'asset_invites' -> HashMap<InvitedUserPrincipal, Invite>. If 'InvitedUserPrincipal' exists in HashMap then append new invite
+			// Record the new chunks of a re-uploaded file and keep the previous chunks as a version. Old chunks are
+			// only deleted once they are no longer referenced
+			if let Some(asset_id) = existing_asset_id {
+				let (asset, pruned_chunks) = Self::replace_chunks(
+					&mut state,
+					asset_id,
+					post_asset.chunks,
+					post_asset.size
+				).ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))?;
 
-			asset_id
-				.and_then(|asset_id| state.assets.get_mut(&asset_id))
-				.map(|found_asset| {
-					// Mutate values
-					found_asset.size = post_asset.size;
-					found_asset.updated_at = time();
+				return Ok((asset, pruned_chunks, vec![]));
+			}
 
-					found_asset.clone()
-				})
-				.unwrap_or_else(|| {
-					// Increment asset ID
-					state.asset_id += 1;
-					let asset_id = state.asset_id;
+			// New assets belong to the owner of the parent folder
+			let owner_principal = match post_asset.parent_id.filter(|parent_id| state.assets.contains_key(parent_id)) {
+				Some(parent_id) => Self::check_permission(&state, &caller_principal, parent_id, Permission::Editor)?.user_id,
+				None => caller_principal,
+			};
 
-					let new_asset = Asset {
-						id: asset_id,
-						user_id: caller_principal,
-						parent_id: post_asset.parent_id,
-						asset_type: post_asset.asset_type,
-						name: post_asset.name,
-						is_favorite: false,
-						size: post_asset.size,
-						extension: post_asset.extension,
-						mime_type: post_asset.mime_type,
-						chunks: post_asset.chunks,
-						settings: post_asset.settings,
-						created_at: time(),
-						updated_at: time(),
-					};
+			let (name, replaced_asset_id) = Self::resolve_name(
+				&state,
+				(owner_principal, post_asset.parent_id),
+				None,
+				&post_asset.name,
+				&post_asset.extension,
+				post_asset.on_conflict,
+				&NameBatch::default()
+			)?;
 
-					// Add new asset or overwrite existing one
-					state.assets.insert(asset_id, new_asset.clone());
+			// Replacing an asset needs the same permission as moving it to the trash
+			let replaced_asset = match replaced_asset_id {
+				Some(replaced_asset_id) => Some(Self::check_permission(&state, &caller_principal, replaced_asset_id, Permission::CoOwner)?),
+				None => None,
+			};
 
-					// Add asset to user_assets
-					state.user_assets.entry(caller_principal).or_default().push(asset_id);
+			// A file replacing a file becomes its new version instead
+			if let Some(replaced_asset) = replaced_asset.filter(|replaced_asset| {
+				post_asset.asset_type == AssetType::File && replaced_asset.asset_type == AssetType::File
+			}) {
+				let (asset, pruned_chunks) = Self::replace_chunks(
+					&mut state,
+					replaced_asset.id,
+					post_asset.chunks,
+					post_asset.size
+				).ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))?;
 
-					new_asset
-				})
-		})
+				return Ok((asset, pruned_chunks, vec![]));
+			}
+
+			// All checks run before the replaced asset is moved to the trash, an error doesn't roll back the state
+			if let Some(url) = &post_asset.settings.url {
+				Self::check_url(&state, url, None)?;
+			}
+
+			let trashed_assets = Self::trash_assets(&mut state, &caller_principal, replaced_asset_id.as_slice())?;
+
+			// Increment asset ID
+			state.asset_id += 1;
+			let asset_id = state.asset_id;
+
+			let hash = Self::file_hash(&post_asset.asset_type, &post_asset.chunks);
+
+			let new_asset = Asset {
+				id: asset_id,
+				user_id: owner_principal,
+				parent_id: post_asset.parent_id,
+				asset_type: post_asset.asset_type,
+				name,
+				is_favorite: false,
+				size: post_asset.size,
+				extension: post_asset.extension,
+				mime_type: post_asset.mime_type,
+				chunks: post_asset.chunks,
+				settings: post_asset.settings,
+				created_at: time(),
+				updated_at: time(),
+				deleted_at: None,
+				tags: None,
+				hash,
+			};
+
+			// Add new asset, including it in the indexes of the owner
+			Self::put_asset(&mut state, new_asset.clone());
+
+			Ok((new_asset, vec![], trashed_assets))
+		})?;
+
+		// Delete the chunks of versions that exceed the maximum number of versions
+		Self::delete_chunks(pruned_chunks).await;
+
+		// Stop serving the replaced assets over HTTP
+		for trashed_asset in &trashed_assets {
+			Self::remove_http_asset(trashed_asset).await;
+		}
+
+		// Start serving a public file over HTTP, or serve the new chunks of a re-uploaded file
+		let asset = Self::publish_http_asset(asset).await;
+
+		Ok(asset)
 	}
 
-	/// Edit asset.
+	/// Get the previous versions of a file.
 	///
 	/// # Arguments
 	/// - `caller_principal` - Principal of the caller
-	/// - `edit_asset` - Asset to edit
+	/// - `asset_id` - Asset ID
 	///
 	/// # Returns
-	/// - `Asset` - Edited asset
-	pub fn edit_asset(caller_principal: Principal, edit_asset: EditAsset) -> Result<Asset, ApiError> {
+	/// - `Vec<AssetVersion>` - Versions, oldest first
+	pub fn get_asset_versions(caller_principal: Principal, asset_id: u32) -> Result<Vec<AssetVersion>, ApiError> {
 		STATE.with(|state| {
+			let state = state.borrow();
+
+			Self::check_permission(&state, &caller_principal, asset_id, Permission::Viewer)?;
+
+			Ok(state.versions.get(&asset_id).unwrap_or_default())
+		})
+	}
+
+	/// Restore a previous version of a file. The current content of the file is kept as a new version.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `asset_id` - Asset ID
+	/// - `version` - Version to restore
+	///
+	/// # Returns
+	/// - `Asset` - Restored asset
+	pub async fn restore_asset_version(caller_principal: Principal, asset_id: u32, version: u32) -> Result<Asset, ApiError> {
+		let (asset, pruned_chunks) = STATE.with(|state| {
 			let mut state = state.borrow_mut();
 
-			// Find all user_assets linked to the principal (caller)
-			let user_asset_ids = state.user_assets.get(&caller_principal).cloned().unwrap_or_default();
-			// Find a specific asset with given value
-			let asset_id = user_asset_ids.into_iter().find(|&asset_id| asset_id == edit_asset.id);
+			Self::check_permission(&state, &caller_principal, asset_id, Permission::Editor)?;
 
-			asset_id
-				.and_then(|asset_id| state.assets.get_mut(&asset_id))
-				.map(|found_asset| {
-					// Mutate values
-					found_asset.parent_id = edit_asset.parent_id;
+			let mut versions = state.versions.get(&asset_id).unwrap_or_default();
 
-					if let Some(name) = edit_asset.name {
-						found_asset.name = name;
-					}
+			let position = versions
+				.iter()
+				.position(|asset_version| asset_version.version == version)
+				.ok_or(ApiError::NotFound("VERSION_NOT_FOUND".to_string()))?;
 
-					match found_asset.asset_type {
-						AssetType::File => {
-							if let Some(extension) = edit_asset.extension {
-								found_asset.extension = extension;
-							}
-						}
-						AssetType::Folder => {
-							found_asset.extension = "".to_string();
-						}
-						AssetType::NFT(_) => {
-							found_asset.extension = "".to_string();
-						}
-					}
+			let asset_version = versions.remove(position);
+			state.versions.insert(asset_id, versions);
 
-					if let Some(is_favorite) = edit_asset.is_favorite {
-						found_asset.is_favorite = is_favorite;
-					}
+			Self::replace_chunks(&mut state, asset_id, asset_version.chunks, asset_version.size).ok_or(
+				ApiError::NotFound("ASSET_NOT_FOUND".to_string())
+			)
+		})?;
 
-					found_asset.updated_at = time();
+		Self::delete_chunks(pruned_chunks).await;
 
-					found_asset.clone()
-				})
-				.ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))
-		})
+		let asset = Self::publish_http_asset(asset).await;
+
+		Ok(asset)
 	}
 
-	/// Move assets to different parent.
+	/// Edit asset.
 	///
 	/// # Arguments
 	/// - `caller_principal` - Principal of the caller
-	/// - `move_assets` - Assets to move
+	/// - `edit_asset` - Asset to edit
 	///
 	/// # Returns
-	/// - `Vec<Asset>` - Moved assets
-	pub fn move_assets(caller_principal: Principal, move_assets: Vec<MoveAsset>) -> Result<Vec<Asset>, ApiError> {
-		STATE.with(|state| {
+	/// - `Asset` - Edited asset
+	pub async fn edit_asset(caller_principal: Principal, edit_asset: EditAsset) -> Result<Asset, ApiError> {
+		let (edited_asset, trashed_assets) = STATE.with(|state| {
 			let mut state = state.borrow_mut();
-			let mut temp: Vec<Asset> = vec![];
 
-			// Find all user_assets linked to the principal (caller)
-			let user_asset_ids = state.user_assets.get(&caller_principal).cloned().unwrap_or_default();
+			let asset = Self::check_permission(&state, &caller_principal, edit_asset.id, Permission::Editor)?;
 
-			for move_asset in move_assets {
-				// Find a specific asset based on the asset to move
-				let asset_id = user_asset_ids
-					.clone()
-					.into_iter()
-					.find(|&asset_id| asset_id == move_asset.id);
+			if asset.parent_id != edit_asset.parent_id {
+				Self::check_move(&state, &caller_principal, &asset, edit_asset.parent_id, &HashMap::new())?;
+			}
+
+			let name = edit_asset.name.unwrap_or_else(|| asset.name.clone());
+			let extension = match asset.asset_type {
+				AssetType::File => edit_asset.extension.unwrap_or_else(|| asset.extension.clone()),
+				AssetType::Folder | AssetType::NFT(_) => "".to_string(),
+			};
 
-				let asset = asset_id
-					.and_then(|asset_id| state.assets.get_mut(&asset_id))
-					.map(|found_asset| {
-						// Mutate values
-						found_asset.parent_id = move_asset.parent_id;
-						found_asset.updated_at = time();
+			// Only a new name or parent can conflict with the other assets in the folder
+			let is_renamed = asset.parent_id != edit_asset.parent_id ||
+				Self::name_key(&name, &extension) != Self::name_key(&asset.name, &asset.extension);
 
-						found_asset.clone()
-					})
-					.ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))?;
+			let (name, replaced_asset_id) = match is_renamed {
+				true =>
+					Self::resolve_name(
+						&state,
+						(asset.user_id, edit_asset.parent_id),
+						Some(asset.id),
+						&name,
+						&extension,
+						edit_asset.on_conflict,
+						&NameBatch::default()
+					)?,
+				false => (name, None),
+			};
 
-				temp.push(asset.clone());
+			// The replaced asset is moved to the trash after the asset is moved out of it
+			if let Some(replaced_asset_id) = replaced_asset_id {
+				Self::check_permission(&state, &caller_principal, replaced_asset_id, Permission::CoOwner)?;
 			}
 
-			Ok(temp)
-		})
+			let mut edited_asset = asset;
+
+			// Mutate values
+			edited_asset.parent_id = edit_asset.parent_id;
+			edited_asset.name = name;
+			edited_asset.extension = extension;
+
+			if let Some(is_favorite) = edit_asset.is_favorite {
+				edited_asset.is_favorite = is_favorite;
+			}
+
+			edited_asset.updated_at = time();
+
+			// Update the folder and search indexes with the new parent, name and extension
+			Self::put_asset(&mut state, edited_asset.clone());
+
+			let trashed_assets = Self::trash_assets(&mut state, &caller_principal, replaced_asset_id.as_slice())?;
+
+			Ok((edited_asset, trashed_assets))
+		})?;
+
+		// Stop serving the replaced assets over HTTP
+		for trashed_asset in &trashed_assets {
+			Self::remove_http_asset(trashed_asset).await;
+		}
+
+		Ok(edited_asset)
 	}
 
-	/// Delete assets.
-	/// If the asset is a folder, all children will be deleted as well.
-	/// If the asset is a file, only the file will be deleted.
+	/// Move assets to different parent.
+	/// All moves are validated before any asset is moved, so either all assets are moved or none.
 	///
 	/// # Arguments
 	/// - `caller_principal` - Principal of the caller
-	/// - `delete_asset_ids` - Asset IDs to delete
+	/// - `move_assets` - Assets to move
 	///
 	/// # Returns
-	/// - `Vec<u32>` - Deleted asset IDs
-	pub fn delete_assets(caller_principal: Principal, delete_asset_ids: Vec<u32>) -> Result<Vec<u32>, ApiError> {
-		STATE.with(|state| {
+	/// - `Vec<Asset>` - Moved assets
+	pub async fn move_assets(caller_principal: Principal, move_assets: Vec<MoveAsset>) -> Result<Vec<Asset>, ApiError> {
+		let (moved_assets, trashed_assets) = STATE.with(|state| {
 			let mut state = state.borrow_mut();
+			let mut temp: Vec<Asset> = vec![];
 
-			// Find all assets linked to the principal (caller)
-			let user_asset_ids = state.user_assets.get(&caller_principal).cloned().unwrap_or_default();
+			// Validate the moves against the tree as it will be after the earlier moves of the batch
+			let mut batch = NameBatch::default();
+			let mut names: Vec<String> = vec![];
+			let mut replaced_asset_ids: Vec<u32> = vec![];
 
-			let source_set: HashSet<u32> = delete_asset_ids.iter().cloned().collect();
-			let target_set: HashSet<u32> = user_asset_ids.iter().cloned().collect();
+			for move_asset in &move_assets {
+				let asset = Self::check_permission(&state, &caller_principal, move_asset.id, Permission::Editor)?;
+				Self::check_move(&state, &caller_principal, &asset, move_asset.parent_id, &batch.moved_parent_ids)?;
 
-			if !source_set.is_subset(&target_set) {
-				return Err(ApiError::NotFound("ASSET_NOT_FOUND".to_string()));
+				let (name, replaced_asset_id) = Self::resolve_name(
+					&state,
+					(asset.user_id, move_asset.parent_id),
+					Some(asset.id),
+					&asset.name,
+					&asset.extension,
+					move_asset.on_conflict,
+					&batch
+				)?;
+
+				let (name_key, extension_key) = Self::name_key(&name, &asset.extension);
+				batch.claimed_names.insert((asset.user_id, move_asset.parent_id, name_key, extension_key), move_asset.id);
+				batch.moved_parent_ids.insert(move_asset.id, move_asset.parent_id);
+
+				// The replaced assets are moved to the trash after all moves are applied
+				if let Some(replaced_asset_id) = replaced_asset_id {
+					Self::check_permission(&state, &caller_principal, replaced_asset_id, Permission::CoOwner)?;
+				}
+
+				names.push(name);
+				replaced_asset_ids.extend(replaced_asset_id);
 			}
 
-			if let Some(assets) = state.user_assets.get_mut(&caller_principal) {
-				assets.retain(|&id| !delete_asset_ids.contains(&id));
+			// Assets that are moved themselves can't be replaced
+			if let Some(asset_id) = replaced_asset_ids.iter().find(|asset_id| batch.moved_parent_ids.contains_key(asset_id)) {
+				return Err(ApiError::AlreadyExists(format!("NAME_ALREADY_EXISTS:{}", asset_id)));
 			}
 
-			state.assets.retain(|&id, _| !delete_asset_ids.contains(&id));
+			for (move_asset, name) in move_assets.into_iter().zip(names) {
+				let mut asset = state.assets.get(&move_asset.id).ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))?;
 
-			Ok(delete_asset_ids)
-		})
+				// Mutate values. Renamed assets get a new number in their name
+				asset.parent_id = move_asset.parent_id;
+				asset.name = name;
+				asset.updated_at = time();
+
+				Self::put_asset(&mut state, asset.clone());
+
+				temp.push(asset);
+			}
+
+			let trashed_assets = Self::trash_assets(&mut state, &caller_principal, &replaced_asset_ids)?;
+
+			Ok((temp, trashed_assets))
+		})?;
+
+		// Stop serving the replaced assets over HTTP
+		for trashed_asset in &trashed_assets {
+			Self::remove_http_asset(trashed_asset).await;
+		}
+
+		Ok(moved_assets)
 	}
 
-	/// Delete existing chunks of an asset. This is used when a user uploads a new version of an asset. The old chunks will be deleted.
+	/// Copy assets to a folder. Folders are copied including all of their children and the chunks of files are
+	/// copied as well. Copies get new IDs, belong to the owner of the folder they are copied to and don't keep the
+	/// URL slug of the original. Either all assets are copied or none.
 	///
 	/// # Arguments
 	/// - `caller_principal` - Principal of the caller
-	/// - `post_asset` - Asset to delete chunks from
+	/// - `copy_assets` - Assets to copy and the parents to copy them to
 	///
 	/// # Returns
-	/// - `()` - No return value
-	async fn delete_existing_chunks(caller_principal: &Principal, post_asset: &PostAsset) {
-		// If existing asset, then do intercanister call to previous delete_chunks
-		if let Some(asset_id) = post_asset.id {
-			// Get user assets using get_user_assets call
-			let user_assets = Self::get_user_assets(caller_principal.clone());
+	/// - `Vec<Asset>` - Copied assets, including the children of copied folders
+	pub async fn copy_assets(caller_principal: Principal, copy_assets: Vec<CopyAsset>) -> Result<Vec<Asset>, ApiError> {
+		// Find the assets to copy
+		let copy_trees = STATE.with(|state| {
+			let state = state.borrow();
+			let mut copy_trees: Vec<(Principal, Vec<Asset>)> = vec![];
 
-			// Find a specific asset by id
-			let asset = user_assets.into_iter().find(|asset| asset.id == asset_id);
+			for copy_asset in &copy_assets {
+				let owner_principal = Self::check_copy(&state, &caller_principal, copy_asset)?;
+				copy_trees.push((owner_principal, Self::find_copy_tree(&state, copy_asset.id)));
+			}
 
-			if let Some(asset) = asset {
-				// Get chunks from asset
-				let chunks = asset.chunks;
+			Ok(copy_trees)
+		})?;
 
-				// Get all chunk ids
-				let chunk_ids: Vec<u32> = chunks
-					.iter()
-					.map(|chunk| chunk.id)
-					.collect();
+		// Group the chunks by the owner of the copies. Chunks stay in the same canister when the owner doesn't
+		// change, otherwise they are copied to the chunks canister of the new owner
+		let mut chunk_groups: HashMap<(Principal, bool), Vec<(CopyKey, Chunk)>> = HashMap::new();
+
+		for (tree_index, (owner_principal, assets)) in copy_trees.iter().enumerate() {
+			for asset in assets.iter().filter(|asset| !asset.chunks.is_empty()) {
+				let is_new_owner = asset.user_id != *owner_principal;
 
-				// Get first chunk's canister principal
-				let caniser_principal = chunks.first().unwrap().canister;
+				chunk_groups
+					.entry((*owner_principal, is_new_owner))
+					.or_default()
+					.extend(asset.chunks.iter().map(|chunk| {
+						// Chunks that don't record their owner were uploaded by the owner of the asset
+						let chunk = Chunk {
+							owner: Some(chunk.owner.unwrap_or(asset.user_id)),
+							..chunk.clone()
+						};
 
-				let _: Result<(Result<Vec<u32>, ApiError>,), _> = call::call(
-					caniser_principal.clone(),
-					"delete_chunks_intercanister_call",
-					(caller_principal.clone(), chunk_ids.clone())
-				).await;
+						((tree_index, asset.id), chunk)
+					}));
 			}
 		}
-	}
 
-	// TODO: get_shared_assets(principal) -> exactly the same as 'get_user_assets' but then for shared_assets
-	// TODO: get_shared_with(principal, id) -> get a list of people with who my asset is shared with -> have option to invoke
-	// TODO: get_invites(principal)
-	// TODO: accept_invite(principal, id) -> check if invite exists -> check if invite is expired -> check if asset exists -> check if asset is Privacy::Private -> add asset to 'shared' HashMap -> add user to 'shared_with' HashMap
-	// TODO: decline_invite(principal, id) -> check if invite exists -> check if invite is expired -> decline
-	// TODO: get_public_asset(id) -> check if asset exists -> check if asset is public -> return asset -> view asset in front-end (in dialog?)
+		// Copy the chunks of all files before any asset is added
+		let mut copied_chunks: HashMap<CopyKey, Vec<Chunk>> = HashMap::new();
+		let mut all_copied_chunks: Vec<(Principal, Chunk)> = vec![];
+
+		for ((owner_principal, is_new_owner), chunks) in chunk_groups {
+			let target_canister = match is_new_owner {
+				true => Self::find_chunks_canister(owner_principal).await.map(Some),
+				false => Ok(None),
+			};
+
+			let result = match target_canister {
+				Ok(target_canister) => Self::copy_chunks(chunks, owner_principal, target_canister).await,
+				Err(err) => Err(err),
+			};
+
+			match result {
+				Ok(chunks) => {
+					for (key, chunk) in chunks {
+						all_copied_chunks.push((owner_principal, chunk.clone()));
+						copied_chunks.entry(key).or_default().push(chunk);
+					}
+				}
+				Err(err) => {
+					Self::delete_chunks(all_copied_chunks).await;
+					return Err(err);
+				}
+			}
+		}
+
+		// Chunks are copied per canister, so they are put back in the order of their index
+		for chunks in copied_chunks.values_mut() {
+			chunks.sort_by_key(|chunk| chunk.index);
+		}
+
+		let result = STATE.with(|state| {
+			let mut state = state.borrow_mut();
+			let mut copies: Vec<Asset> = vec![];
+
+			// The folders to copy to might have changed while the chunks were copied
+			let mut batch = NameBatch::default();
+			let mut names: Vec<String> = vec![];
+			let mut replaced_asset_ids: Vec<u32> = vec![];
+
+			for (copy_asset, (owner_principal, assets)) in copy_assets.iter().zip(&copy_trees) {
+				Self::check_copy(&state, &caller_principal, copy_asset)?;
+
+				let asset = assets.first().ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))?;
+
+				let (name, replaced_asset_id) = Self::resolve_name(
+					&state,
+					(*owner_principal, copy_asset.parent_id),
+					None,
+					&asset.name,
+					&asset.extension,
+					copy_asset.on_conflict,
+					&batch
+				)?;
+
+				// The replaced assets are moved to the trash after all copies are added
+				if let Some(replaced_asset_id) = replaced_asset_id {
+					Self::check_permission(&state, &caller_principal, replaced_asset_id, Permission::CoOwner)?;
+				}
+
+				// Copies of the same batch get different names
+				let (name_key, extension_key) = Self::name_key(&name, &asset.extension);
+				batch.claimed_names.insert((*owner_principal, copy_asset.parent_id, name_key, extension_key), asset.id);
+
+				names.push(name);
+				replaced_asset_ids.extend(replaced_asset_id);
+			}
+
+			for (tree_index, ((copy_asset, (owner_principal, assets)), name)) in copy_assets
+				.iter()
+				.zip(&copy_trees)
+				.zip(names)
+				.enumerate()
+			{
+				// Map the IDs of the originals to the IDs of the copies, so children end up in the copied folders
+				let mut copy_ids: HashMap<u32, u32> = HashMap::new();
+
+				for asset in assets {
+					// The copied asset goes to the target folder with its resolved name, its children to the copied
+					// folders
+					let (parent_id, name) = if asset.id == copy_asset.id {
+						(copy_asset.parent_id, name.clone())
+					} else {
+						(asset.parent_id.and_then(|parent_id| copy_ids.get(&parent_id).copied()), asset.name.clone())
+					};
+
+					// Increment asset ID
+					state.asset_id += 1;
+					let asset_id = state.asset_id;
+					copy_ids.insert(asset.id, asset_id);
+
+					let copy = Asset {
+						id: asset_id,
+						user_id: *owner_principal,
+						parent_id,
+						asset_type: asset.asset_type.clone(),
+						name,
+						is_favorite: false,
+						size: asset.size,
+						extension: asset.extension.clone(),
+						mime_type: asset.mime_type.clone(),
+						created_at: time(),
+						updated_at: time(),
+						deleted_at: None,
+						tags: asset.tags.clone(),
+						chunks: copied_chunks.remove(&(tree_index, asset.id)).unwrap_or_default(),
+						settings: Settings {
+							privacy: asset.settings.privacy.clone(),
+							url: None,
+						},
+						hash: asset.hash.clone(),
+					};
+
+					Self::put_asset(&mut state, copy.clone());
+
+					copies.push(copy);
+				}
+			}
+
+			let trashed_assets = Self::trash_assets(&mut state, &caller_principal, &replaced_asset_ids)?;
+
+			Ok((copies, trashed_assets))
+		});
+
+		let (copies, trashed_assets) = match result {
+			Ok(result) => result,
+			Err(err) => {
+				Self::delete_chunks(all_copied_chunks).await;
+				return Err(err);
+			}
+		};
+
+		// Stop serving the replaced assets over HTTP
+		for trashed_asset in &trashed_assets {
+			Self::remove_http_asset(trashed_asset).await;
+		}
+
+		// Serve the public copies over HTTP
+		let mut published_copies: Vec<Asset> = vec![];
+
+		for copy in copies {
+			published_copies.push(Self::publish_http_asset(copy).await);
+		}
+
+		Ok(published_copies)
+	}
+
+	/// Edit the settings of an asset. Setting an empty URL removes the URL slug of the asset.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `edit_settings` - Settings to edit
+	///
+	/// # Returns
+	/// - `Asset` - Edited asset
+	pub async fn edit_settings(caller_principal: Principal, edit_settings: EditSettings) -> Result<Asset, ApiError> {
+		let asset = STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			let mut asset = Self::check_permission(&state, &caller_principal, edit_settings.id, Permission::CoOwner)?;
+
+			let url = match edit_settings.url {
+				Some(url) if url.is_empty() => None,
+				Some(url) => {
+					Self::check_url(&state, &url, Some(edit_settings.id))?;
+					Some(url)
+				}
+				None => asset.settings.url.clone(),
+			};
+
+			// Mutate values
+			if let Some(privacy) = edit_settings.privacy {
+				asset.settings.privacy = privacy;
+			}
+
+			asset.settings.url = url;
+			asset.updated_at = time();
+
+			// Update the asset and the URL index
+			Self::put_asset(&mut state, asset.clone());
+
+			Ok(asset)
+		})?;
+
+		// Start or stop serving the asset over HTTP. An asset that can't be served is made private
+		if let Err(err) = Self::sync_http_asset(&asset).await {
+			Self::make_private(asset).await;
+			return Err(err);
+		}
+
+		Ok(asset)
+	}
+
+	/// Move assets to the trash.
+	/// If the asset is a folder, all children will be moved to the trash as well.
+	/// If the asset is a file, only the file will be moved to the trash.
+	/// Assets in the trash are permanently deleted after the trash retention or when the trash is emptied.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `delete_asset_ids` - Asset IDs to delete
+	///
+	/// # Returns
+	/// - `Vec<u32>` - Deleted asset IDs, including the children of deleted folders
+	pub async fn delete_assets(caller_principal: Principal, delete_asset_ids: Vec<u32>) -> Result<Vec<u32>, ApiError> {
+		let deleted_assets = STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			Self::trash_assets(&mut state, &caller_principal, &delete_asset_ids)
+		})?;
+
+		// Stop serving the deleted assets over HTTP
+		for asset in &deleted_assets {
+			Self::remove_http_asset(asset).await;
+		}
+
+		Ok(
+			deleted_assets
+				.iter()
+				.map(|asset| asset.id)
+				.collect()
+		)
+	}
+
+	/// Restore assets from the trash. Children that were deleted together with a folder are restored as well.
+	/// If the parent of a restored asset is no longer available, the asset is restored to the root.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `asset_ids` - Asset IDs to restore
+	///
+	/// # Returns
+	/// - `Vec<Asset>` - Restored assets
+	pub async fn restore_assets(caller_principal: Principal, asset_ids: Vec<u32>) -> Result<Vec<Asset>, ApiError> {
+		let restored_assets = STATE.with(|state| {
+			let mut state = state.borrow_mut();
+			let mut restore_asset_ids: Vec<u32> = vec![];
+			let mut listed_asset_ids: HashSet<u32> = HashSet::new();
+
+			// Only the owner can restore assets from the trash
+			for asset_id in &asset_ids {
+				let asset = Self::find_owned_asset(&state, &caller_principal, *asset_id)?;
+
+				let Some(deleted_at) = asset.deleted_at else {
+					return Err(ApiError::NotFound("ASSET_NOT_IN_TRASH".to_string()));
+				};
+
+				if listed_asset_ids.insert(asset.id) {
+					restore_asset_ids.push(asset.id);
+				}
+
+				if let AssetType::Folder = asset.asset_type {
+					restore_asset_ids.extend(
+						Self::find_child_asset_ids(&state, &asset)
+							.into_iter()
+							.filter(|child_id| state.assets.get(child_id).is_some_and(|child| child.deleted_at == Some(deleted_at)))
+							.filter(|child_id| listed_asset_ids.insert(*child_id))
+					);
+				}
+			}
+
+			Ok(Self::restore_listed_assets(&mut state, &restore_asset_ids, time()))
+		})?;
+
+		// Serve the restored public assets over HTTP again
+		let mut published_assets: Vec<Asset> = vec![];
+
+		for asset in restored_assets {
+			published_assets.push(Self::publish_http_asset(asset).await);
+		}
+
+		Ok(published_assets)
+	}
+
+	/// Restore exactly the listed assets from the trash, parents before their children. Assets whose folder is
+	/// still in the trash are restored to the root folder, and assets whose name is taken in the destination
+	/// folder in the meantime are renamed.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `asset_ids` - Asset IDs to restore
+	/// - `now` - Current time
+	///
+	/// # Returns
+	/// - `Vec<Asset>` - Restored assets
+	fn restore_listed_assets(state: &mut AssetsStore, asset_ids: &[u32], now: u64) -> Vec<Asset> {
+		let mut restored_assets: Vec<Asset> = vec![];
+
+		for asset_id in asset_ids {
+			let Some(mut asset) = state.assets.get(asset_id) else {
+				continue;
+			};
+
+			let is_parent_available = asset.parent_id
+				.is_none_or(|parent_id| state.assets.get(&parent_id).is_some_and(|parent| parent.deleted_at.is_none()));
+
+			if !is_parent_available {
+				asset.parent_id = None;
+			}
+
+			// Renaming never fails, the asset itself and assets still in the trash don't conflict
+			if let Ok((name, _)) = Self::resolve_name(
+				state,
+				(asset.user_id, asset.parent_id),
+				Some(asset.id),
+				&asset.name,
+				&asset.extension,
+				Some(NameConflict::Rename),
+				&NameBatch::default()
+			) {
+				asset.name = name;
+			}
+
+			asset.deleted_at = None;
+			asset.updated_at = now;
+
+			Self::put_asset(state, asset.clone());
+			restored_assets.push(asset);
+		}
+
+		restored_assets
+	}
+
+	/// Permanently delete all assets in the trash of the caller, including the chunks of deleted files.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<u32>` - Permanently deleted asset IDs
+	pub async fn empty_trash(caller_principal: Principal) -> Vec<u32> {
+		let (deleted_assets, deleted_chunks) = STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			let trash_asset_ids: Vec<u32> = Self::find_user_assets(&state, &caller_principal)
+				.iter()
+				.filter(|asset| asset.deleted_at.is_some())
+				.map(|asset| asset.id)
+				.collect();
+
+			Self::remove_assets(&mut state, &trash_asset_ids)
+		});
+
+		Self::delete_chunks(deleted_chunks).await;
+
+		deleted_assets
+			.iter()
+			.map(|asset| asset.id)
+			.collect()
+	}
+
+	/// Get the tags used on the assets of the caller, including the number of assets per tag and the colour
+	/// label of the tag. Tags that only have a colour label are returned with a count of zero.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Tag>` - Tags sorted by name
+	pub fn get_tags(caller_principal: Principal) -> Vec<Tag> {
+		STATE.with(|state| {
+			let state = state.borrow();
+			let mut counts: BTreeMap<String, u32> = BTreeMap::new();
+
+			for asset in Self::find_user_assets(&state, &caller_principal) {
+				if asset.deleted_at.is_some() {
+					continue;
+				}
+
+				for tag in asset.tags.unwrap_or_default() {
+					*counts.entry(tag).or_default() += 1;
+				}
+			}
+
+			let tag_colors: HashMap<String, String> = state.tag_colors
+				.prefix(&caller_principal)
+				.map(|((_, tag), color)| (tag, color))
+				.collect();
+
+			for tag in tag_colors.keys() {
+				counts.entry(tag.clone()).or_default();
+			}
+
+			counts
+				.into_iter()
+				.map(|(name, count)| Tag {
+					color: tag_colors.get(&name).cloned(),
+					name,
+					count,
+				})
+				.collect()
+		})
+	}
+
+	/// Add tags to assets. Either all assets are tagged or none.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `edit_tags` - Assets and the tags to add
+	///
+	/// # Returns
+	/// - `Vec<Asset>` - Tagged assets
+	pub fn add_tags(caller_principal: Principal, edit_tags: EditTags) -> Result<Vec<Asset>, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			let tags = Self::check_tags(edit_tags.tags)?;
+
+			for asset_id in &edit_tags.asset_ids {
+				let asset = Self::check_permission(&state, &caller_principal, *asset_id, Permission::Editor)?;
+
+				let asset_tags = asset.tags.as_deref().unwrap_or_default();
+				let new_tags_count = tags
+					.iter()
+					.filter(|tag| !asset_tags.contains(tag))
+					.count();
+
+				if asset_tags.len() + new_tags_count > MAX_ASSET_TAGS {
+					return Err(ApiError::BadRequest("TOO_MANY_TAGS".to_string()));
+				}
+			}
+
+			let mut tagged_assets: Vec<Asset> = vec![];
+
+			for asset_id in edit_tags.asset_ids {
+				if let Some(mut asset) = state.assets.get(&asset_id) {
+					let asset_tags = asset.tags.get_or_insert_with(Vec::new);
+
+					for tag in &tags {
+						if !asset_tags.contains(tag) {
+							asset_tags.push(tag.clone());
+						}
+					}
+
+					Self::put_asset(&mut state, asset.clone());
+					tagged_assets.push(asset);
+				}
+			}
+
+			Ok(tagged_assets)
+		})
+	}
+
+	/// Remove tags from assets. Either the tags are removed from all assets or from none.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `edit_tags` - Assets and the tags to remove
+	///
+	/// # Returns
+	/// - `Vec<Asset>` - Untagged assets
+	pub fn remove_tags(caller_principal: Principal, edit_tags: EditTags) -> Result<Vec<Asset>, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			for asset_id in &edit_tags.asset_ids {
+				Self::check_permission(&state, &caller_principal, *asset_id, Permission::Editor)?;
+			}
+
+			let tags: Vec<String> = edit_tags.tags
+				.iter()
+				.map(|tag| tag.trim().to_string())
+				.collect();
+
+			let mut untagged_assets: Vec<Asset> = vec![];
+
+			for asset_id in edit_tags.asset_ids {
+				if let Some(mut asset) = state.assets.get(&asset_id) {
+					if let Some(asset_tags) = asset.tags.as_mut() {
+						asset_tags.retain(|tag| !tags.contains(tag));
+					}
+
+					if asset.tags.as_ref().is_some_and(|asset_tags| asset_tags.is_empty()) {
+						asset.tags = None;
+					}
+
+					Self::put_asset(&mut state, asset.clone());
+					untagged_assets.push(asset);
+				}
+			}
+
+			Ok(untagged_assets)
+		})
+	}
+
+	/// Set the colour label of a tag of the caller. Setting no colour removes the colour label.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `tag` - Tag
+	/// - `color` - Colour as hex code, e.g. `#ff0000`
+	///
+	/// # Returns
+	/// - `Tag` - Tag with its colour label
+	pub fn set_tag_color(caller_principal: Principal, tag: String, color: Option<String>) -> Result<Tag, ApiError> {
+		let name = Self::check_tags(vec![tag])?.remove(0);
+
+		if let Some(color) = &color {
+			let is_valid =
+				color.len() == 7 && color.starts_with('#') && color.chars().skip(1).all(|c| c.is_ascii_hexdigit());
+
+			if !is_valid {
+				return Err(ApiError::BadRequest("INVALID_COLOR".to_string()));
+			}
+		}
+
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			match &color {
+				Some(color) => state.tag_colors.insert((caller_principal, name.clone()), color.clone()),
+				None => {
+					state.tag_colors.remove(&(caller_principal, name.clone()));
+				}
+			}
+
+			let count = Self::find_user_assets(&state, &caller_principal)
+				.iter()
+				.filter(|asset| asset.deleted_at.is_none() && Self::has_tag(asset, &name))
+				.count() as u32;
+
+			Ok(Tag { name, color, count })
+		})
+	}
+
+	/// Invite a user to a private asset. The usernames of both users are looked up in the users canister.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `post_invite` - Invite to send
+	///
+	/// # Returns
+	/// - `Invite` - Sent invite
+	pub async fn send_invite(caller_principal: Principal, post_invite: PostInvite) -> Result<Invite, ApiError> {
+		if post_invite.principal == caller_principal {
+			return Err(ApiError::BadRequest("CANNOT_INVITE_SELF".to_string()));
+		}
+
+		// Only registered users can be invited
+		let invited_user = Self::get_user(post_invite.principal).await?;
+		let invited_by_user = Self::get_user(caller_principal).await?;
+
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			if Self::is_expired(post_invite.expires_at) {
+				return Err(ApiError::BadRequest("INVITE_EXPIRED".to_string()));
+			}
+
+			// Only the owner of the asset can invite other users
+			let asset = Self::find_owned_asset(&state, &caller_principal, post_invite.asset_id)?;
+
+			if asset.deleted_at.is_some() {
+				return Err(ApiError::NotFound("ASSET_NOT_FOUND".to_string()));
+			}
+
+			if let Privacy::Public = asset.settings.privacy {
+				return Err(ApiError::BadRequest("ASSET_NOT_PRIVATE".to_string()));
+			}
+
+			// Check if the asset is already shared with the invited user
+			let is_shared = state.shared_with
+				.get(&(caller_principal, post_invite.asset_id))
+				.is_some_and(|shared_with| shared_with.iter().any(|user| user.principal == post_invite.principal));
+
+			if is_shared {
+				return Err(ApiError::AlreadyExists("ASSET_ALREADY_SHARED".to_string()));
+			}
+
+			// Check if there is already a pending invite for the asset
+			let is_invited = state.asset_invites
+				.prefix(&post_invite.principal)
+				.any(
+					|(_, invite)|
+						invite.asset_id == post_invite.asset_id &&
+						invite.status == InviteStatus::Pending &&
+						!Self::is_expired(invite.expires_at)
+				);
+
+			if is_invited {
+				return Err(ApiError::AlreadyExists("INVITE_ALREADY_SENT".to_string()));
+			}
+
+			// Increment invite ID
+			state.invite_id += 1;
+			let invite_id = state.invite_id;
+
+			let invite = Invite {
+				id: invite_id,
+				invited_by_principal: caller_principal,
+				invited_by_username: invited_by_user.username,
+				invited_username: invited_user.username,
+				asset_id: post_invite.asset_id,
+				permission: post_invite.permission,
+				status: InviteStatus::Pending,
+				expires_at: post_invite.expires_at,
+			};
+
+			state.asset_invites.insert((post_invite.principal, invite_id), invite.clone());
+			state.asset_invite_ids.insert((invite.asset_id, post_invite.principal, invite_id), ());
+
+			Ok(invite)
+		})
+	}
+
+	/// Get pending invites of the caller.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Invite>` - Pending invites that are not expired
+	pub fn get_invites(caller_principal: Principal) -> Vec<Invite> {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			state.asset_invites
+				.prefix(&caller_principal)
+				.map(|(_, invite)| invite)
+				.filter(|invite| invite.status == InviteStatus::Pending && !Self::is_expired(invite.expires_at))
+				.collect()
+		})
+	}
+
+	/// Accept an invite. The asset will be added to the caller's shared assets.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `invite_id` - Invite ID
+	///
+	/// # Returns
+	/// - `Asset` - Shared asset
+	pub fn accept_invite(caller_principal: Principal, invite_id: u32) -> Result<Asset, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			let invite = Self::find_pending_invite(&state, &caller_principal, invite_id)?;
+			let asset_id = invite.asset_id;
+
+			// The asset must still exist, be owned by the inviter and be private
+			let asset = state.assets
+				.get(&asset_id)
+				.filter(|asset| asset.user_id == invite.invited_by_principal && asset.deleted_at.is_none())
+				.ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))?;
+
+			if let Privacy::Public = asset.settings.privacy {
+				return Err(ApiError::BadRequest("ASSET_NOT_PRIVATE".to_string()));
+			}
+
+			state.asset_invites.insert((caller_principal, invite_id), Invite {
+				status: InviteStatus::Accepted,
+				..invite.clone()
+			});
+
+			// Add asset to the caller's shared assets
+			state.shared.insert((caller_principal, asset_id), ());
+
+			// Add caller to the list of people the owner shared the asset with
+			let mut shared_with = state.shared_with.get(&(invite.invited_by_principal, asset_id)).unwrap_or_default();
+			if !shared_with.iter().any(|user| user.principal == caller_principal) {
+				shared_with.push(SharedWith {
+					principal: caller_principal,
+					username: invite.invited_username,
+					permission: invite.permission,
+				});
+
+				state.shared_with.insert((invite.invited_by_principal, asset_id), shared_with);
+			}
+
+			Ok(asset)
+		})
+	}
+
+	/// Decline an invite.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `invite_id` - Invite ID
+	///
+	/// # Returns
+	/// - `Invite` - Declined invite
+	pub fn decline_invite(caller_principal: Principal, invite_id: u32) -> Result<Invite, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			let mut invite = Self::find_pending_invite(&state, &caller_principal, invite_id)?;
+			invite.status = InviteStatus::Declined;
+
+			state.asset_invites.insert((caller_principal, invite_id), invite.clone());
+
+			Ok(invite)
+		})
+	}
+
+	/// Get the list of users an asset is shared with.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `Vec<SharedWith>` - Users that have access to the asset
+	pub fn get_shared_with(caller_principal: Principal, asset_id: u32) -> Result<Vec<SharedWith>, ApiError> {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			Self::find_owned_asset(&state, &caller_principal, asset_id)?;
+
+			Ok(state.shared_with.get(&(caller_principal, asset_id)).unwrap_or_default())
+		})
+	}
+
+	/// Revoke the access of a user to an asset. Pending invites of the user for the asset are cancelled.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `asset_id` - Asset ID
+	/// - `principal` - Principal of the user to revoke
+	///
+	/// # Returns
+	/// - `Vec<SharedWith>` - Users that still have access to the asset
+	pub fn revoke_access(
+		caller_principal: Principal,
+		asset_id: u32,
+		principal: Principal
+	) -> Result<Vec<SharedWith>, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			Self::find_owned_asset(&state, &caller_principal, asset_id)?;
+
+			let is_invite_cancelled = Self::cancel_invites(&mut state, asset_id, principal);
+
+			let opt_shared_with = state.shared_with
+				.get(&(caller_principal, asset_id))
+				.filter(|shared_with| shared_with.iter().any(|user| user.principal == principal));
+
+			let mut shared_with = match opt_shared_with {
+				Some(shared_with) => shared_with,
+				None if is_invite_cancelled => {
+					return Ok(state.shared_with.get(&(caller_principal, asset_id)).unwrap_or_default());
+				}
+				None => {
+					return Err(ApiError::NotFound("SHARED_WITH_NOT_FOUND".to_string()));
+				}
+			};
+
+			// Remove user from the list of people that have access to the asset
+			shared_with.retain(|user| user.principal != principal);
+
+			match shared_with.is_empty() {
+				true => {
+					state.shared_with.remove(&(caller_principal, asset_id));
+				}
+				false => state.shared_with.insert((caller_principal, asset_id), shared_with.clone()),
+			}
+
+			// Remove asset from the user's shared assets
+			state.shared.remove(&(principal, asset_id));
+
+			Ok(shared_with)
+		})
+	}
+
+	/// Move assets and all nested children of folders to the trash. Non-owners need to be co-owner of all assets
+	/// before anything is moved.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `caller_principal` - Principal of the caller
+	/// - `asset_ids` - Asset IDs to move to the trash
+	///
+	/// # Returns
+	/// - `Vec<Asset>` - Assets moved to the trash, including the children of folders
+	fn trash_assets(state: &mut AssetsStore, caller_principal: &Principal, asset_ids: &[u32]) -> Result<Vec<Asset>, ApiError> {
+		let mut trash_asset_ids: HashSet<u32> = HashSet::new();
+
+		for asset_id in asset_ids {
+			let asset = Self::check_permission(state, caller_principal, *asset_id, Permission::CoOwner)?;
+
+			trash_asset_ids.insert(asset.id);
+
+			if let AssetType::Folder = asset.asset_type {
+				trash_asset_ids.extend(Self::find_child_asset_ids(state, &asset));
+			}
+		}
+
+		let deleted_at = time();
+		let mut trashed_assets: Vec<Asset> = vec![];
+
+		for asset_id in trash_asset_ids {
+			// Children that are already in the trash keep their own deletion time
+			if let Some(mut asset) = state.assets.get(&asset_id).filter(|asset| asset.deleted_at.is_none()) {
+				asset.deleted_at = Some(deleted_at);
+
+				Self::put_asset(state, asset.clone());
+				trashed_assets.push(asset);
+			}
+		}
+
+		Ok(trashed_assets)
+	}
+
+	/// Permanently remove assets and all nested children of folders from the store, including their versions.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `asset_ids` - Asset IDs to remove
+	///
+	/// # Returns
+	/// - `(Vec<Asset>, Vec<(Principal, Chunk)>)` - Removed assets and the chunks of them and their versions by owner
+	fn remove_assets(state: &mut AssetsStore, asset_ids: &[u32]) -> (Vec<Asset>, Vec<(Principal, Chunk)>) {
+		let mut remove_asset_ids: HashSet<u32> = HashSet::new();
+
+		for asset_id in asset_ids {
+			let Some(asset) = state.assets.get(asset_id) else {
+				continue;
+			};
+
+			remove_asset_ids.insert(asset.id);
+
+			// Children are always owned by the owner of the folder
+			if let AssetType::Folder = asset.asset_type {
+				remove_asset_ids.extend(Self::find_child_asset_ids(state, &asset));
+			}
+		}
+
+		let remove_asset_ids: Vec<u32> = remove_asset_ids.into_iter().collect();
+
+		Self::remove_listed_assets(state, &remove_asset_ids)
+	}
+
+	/// Permanently remove assets from the store, including their versions. Nested children of folders are not
+	/// removed.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `asset_ids` - Asset IDs to remove
+	///
+	/// # Returns
+	/// - `(Vec<Asset>, Vec<(Principal, Chunk)>)` - Removed assets and the chunks of them and their versions by owner
+	fn remove_listed_assets(state: &mut AssetsStore, asset_ids: &[u32]) -> (Vec<Asset>, Vec<(Principal, Chunk)>) {
+		let removed_assets: Vec<Asset> = asset_ids
+			.iter()
+			.filter_map(|asset_id| Self::delete_asset(state, *asset_id))
+			.collect();
+
+		let mut removed_chunks: Vec<(Principal, Chunk)> = vec![];
+
+		for asset in &removed_assets {
+			let versions = state.versions.remove(&asset.id).unwrap_or_default();
+
+			removed_chunks.extend(
+				asset.chunks
+					.iter()
+					.chain(versions.iter().flat_map(|version| version.chunks.iter()))
+					.map(|chunk| (asset.user_id, chunk.clone()))
+			);
+
+			// Remove the asset from everyone it was shared with or invited to
+			for user in state.shared_with.remove(&(asset.user_id, asset.id)).unwrap_or_default() {
+				state.shared.remove(&(user.principal, asset.id));
+			}
+
+			let invite_keys: Vec<(u32, Principal, u32)> = state.asset_invite_ids.prefix_keys(&asset.id).collect();
+
+			for (asset_id, principal, invite_id) in invite_keys {
+				state.asset_invites.remove(&(principal, invite_id));
+				state.asset_invite_ids.remove(&(asset_id, principal, invite_id));
+			}
+		}
+
+		(removed_assets, removed_chunks)
+	}
+
+	/// Copy chunks. Chunks are copied within the canister that stores them with one call per canister, unless a
+	/// target canister is given. Chunks copied to another canister are passed one by one, as a call can't hold more
+	/// than one chunk. If a chunk can't be copied, the chunks that were already copied are deleted. Nothing is
+	/// copied if a chunk isn't stored in a chunks canister of its owner.
+	///
+	/// # Arguments
+	/// - `chunks` - Chunks to copy with a key that is returned with their copies
+	/// - `owner_principal` - Principal of the owner of the copies
+	/// - `target_canister` - Chunks canister to copy the chunks to
+	///
+	/// # Returns
+	/// - `Vec<(K, Chunk)>` - Copied chunks with the keys of the originals
+	async fn copy_chunks<K>(
+		chunks: Vec<(K, Chunk)>,
+		owner_principal: Principal,
+		target_canister: Option<Principal>
+	) -> Result<Vec<(K, Chunk)>, ApiError> {
+		// The blobs are only read once they are known to belong to the owners of the chunks
+		let original_chunks: Vec<Chunk> = chunks
+			.iter()
+			.map(|(_, chunk)| chunk.clone())
+			.collect();
+
+		Self::check_chunk_owners(&original_chunks, owner_principal).await?;
+
+		let mut canister_chunks: HashMap<Principal, Vec<(K, Chunk)>> = HashMap::new();
+		let mut moved_chunks: Vec<(K, Chunk)> = vec![];
+
+		for (key, chunk) in chunks {
+			match target_canister.filter(|target_canister| *target_canister != chunk.canister) {
+				Some(_) => moved_chunks.push((key, chunk)),
+				None => canister_chunks.entry(chunk.canister).or_default().push((key, chunk)),
+			}
+		}
+
+		let mut copied_chunks: Vec<(K, Chunk)> = vec![];
+		let mut result: Result<(), ApiError> = Ok(());
+
+		for (canister, chunks) in canister_chunks {
+			let (keys, chunks): (Vec<K>, Vec<Chunk>) = chunks.into_iter().unzip();
+
+			// The copies are returned in the order of the chunks
+			match Self::call_result(call::call(canister, "copy_chunks_intercanister_call", (chunks,)).await) {
+				Ok(copies) => copied_chunks.extend(keys.into_iter().zip::<Vec<Chunk>>(copies)),
+				Err(err) => {
+					result = Err(err);
+					break;
+				}
+			}
+		}
+
+		if let Some(target_canister) = target_canister.filter(|_| result.is_ok()) {
+			for (key, chunk) in moved_chunks {
+				let copy = match Self::call_result(call::call(chunk.canister, "get_chunk_intercanister_call", (chunk.id,)).await) {
+					Ok(blob) =>
+						Self::call_result(
+							call::call(target_canister, "add_chunk_intercanister_call", (
+								PostChunk {
+									blob,
+									index: chunk.index,
+								},
+							)).await
+						),
+					Err(err) => Err(err),
+				};
+
+				match copy {
+					Ok(copy) => copied_chunks.push((key, copy)),
+					Err(err) => {
+						result = Err(err);
+						break;
+					}
+				}
+			}
+		}
+
+		if let Err(err) = result {
+			Self::delete_chunks(
+				copied_chunks
+					.into_iter()
+					.map(|(_, chunk)| (owner_principal, chunk))
+					.collect()
+			).await;
+
+			return Err(err);
+		}
+
+		Ok(copied_chunks)
+	}
+
+	/// Get the result of an intercanister call.
+	///
+	/// # Arguments
+	/// - `result` - Result of the call
+	///
+	/// # Returns
+	/// - `T` - Value returned by the called canister
+	fn call_result<T>(result: CallResult<(Result<T, ApiError>,)>) -> Result<T, ApiError> {
+		match result {
+			Ok((result,)) => result,
+			Err((code, message)) => Err(ApiError::CanisterFailed(CanisterFailedError { code, message })),
+		}
+	}
+
+	/// Set the hashes of chunks to the hashes of the blobs stored in the chunks canisters, with one call per
+	/// chunks canister.
+	///
+	/// # Arguments
+	/// - `chunks` - Chunks
+	///
+	/// # Returns
+	/// - `Vec<Chunk>` - Chunks with the hashes of the stored blobs
+	async fn stored_chunk_hashes(mut chunks: Vec<Chunk>) -> Result<Vec<Chunk>, ApiError> {
+		let mut canister_chunks: HashMap<Principal, Vec<usize>> = HashMap::new();
+
+		for (position, chunk) in chunks.iter().enumerate() {
+			canister_chunks.entry(chunk.canister).or_default().push(position);
+		}
+
+		for (canister, positions) in canister_chunks {
+			let chunk_ids: Vec<u32> = positions
+				.iter()
+				.map(|position| chunks[*position].id)
+				.collect();
+
+			// The hashes are returned in the order of the chunk IDs
+			let hashes: Vec<Vec<u8>> = Self::call_result(
+				call::call(canister, "get_chunk_hashes_intercanister_call", (chunk_ids,)).await
+			)?;
+
+			for (position, hash) in positions.into_iter().zip(hashes) {
+				chunks[position].hash = Some(hash);
+			}
+		}
+
+		Ok(chunks)
+	}
+
+	/// Find the assets to copy when copying an asset: the asset itself and all children of a folder that are not
+	/// in the trash. Parents come before their children.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `Vec<Asset>` - Assets to copy
+	fn find_copy_tree(state: &AssetsStore, asset_id: u32) -> Vec<Asset> {
+		let mut assets: Vec<Asset> = vec![];
+		let mut visited_asset_ids: HashSet<u32> = HashSet::from([asset_id]);
+		let mut asset_ids = VecDeque::from([asset_id]);
+
+		while let Some(asset_id) = asset_ids.pop_front() {
+			let Some(asset) = state.assets.get(&asset_id).filter(|asset| asset.deleted_at.is_none()) else {
+				continue;
+			};
+
+			if let AssetType::Folder = asset.asset_type {
+				asset_ids.extend(
+					state.folder_assets
+						.prefix_keys(&(asset.user_id, Some(asset.id)))
+						.map(|(_, _, child_id)| child_id)
+						.filter(|child_id| visited_asset_ids.insert(*child_id))
+				);
+			}
+
+			assets.push(asset);
+		}
+
+		assets
+	}
+
+	/// Find the chunks canister of a user. The users canister keeps the chunks canisters of all users, the
+	/// newest one is used.
+	///
+	/// # Arguments
+	/// - `principal` - Principal of the user
+	///
+	/// # Returns
+	/// - `Principal` - Principal of the chunks canister
+	async fn find_chunks_canister(principal: Principal) -> Result<Principal, ApiError> {
+		let user = Self::get_user(principal).await?;
+
+		user.canisters.last().copied().ok_or(ApiError::NotFound("CHUNKS_CANISTER_NOT_FOUND".to_string()))
+	}
+
+	/// Get a user from the users canister.
+	///
+	/// # Arguments
+	/// - `principal` - Principal of the user
+	///
+	/// # Returns
+	/// - `User` - User
+	async fn get_user(principal: Principal) -> Result<User, ApiError> {
+		let users_canister = STATE.with(|state| state.borrow().users_canister).ok_or(
+			ApiError::NotFound("USERS_CANISTER_NOT_FOUND".to_string())
+		)?;
+
+		Self::call_result(call::call(users_canister, "get_user_intercanister_call", (principal,)).await)
+	}
+
+	/// Check that chunks are stored in the chunks canisters of the users they belong to, so an asset can't
+	/// reference the blobs of another user. The users canister is called once per owner.
+	///
+	/// # Arguments
+	/// - `chunks` - Chunks to check
+	/// - `default_owner` - Principal of the owner of chunks that don't record their owner
+	///
+	/// # Returns
+	/// - `()` - No return value
+	async fn check_chunk_owners(chunks: &[Chunk], default_owner: Principal) -> Result<(), ApiError> {
+		let mut owner_canisters: HashMap<Principal, HashSet<Principal>> = HashMap::new();
+
+		for chunk in chunks {
+			owner_canisters.entry(chunk.owner.unwrap_or(default_owner)).or_default().insert(chunk.canister);
+		}
+
+		for (owner, canisters) in owner_canisters {
+			let user = Self::get_user(owner).await?;
+
+			if !canisters.iter().all(|canister| user.canisters.contains(canister)) {
+				return Err(ApiError::Unauthorized("CHUNKS_NOT_OWNED".to_string()));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Delete chunks from the chunks canisters that store them. Chunks that can't be deleted are kept to be
+	/// retried by the next purge of the trash.
+	///
+	/// # Arguments
+	/// - `chunks` - Chunks to delete with the principal of the owner of the asset they belong to
+	///
+	/// # Returns
+	/// - `()` - No return value
+	async fn delete_chunks(chunks: Vec<(Principal, Chunk)>) {
+		Self::delete_chunk_ids(Self::group_chunk_ids(chunks)).await;
+	}
+
+	/// Group chunks by the chunks canister that stores them and the principal their blobs are stored under. Blobs
+	/// are stored under the principal of the uploader, which isn't the owner of the asset for files uploaded by an
+	/// editor. Chunks that don't record their owner were uploaded by the owner of the asset.
+	///
+	/// # Arguments
+	/// - `chunks` - Chunks with the principal of the owner of the asset they belong to
+	///
+	/// # Returns
+	/// - `HashMap<(Principal, Principal), Vec<u32>>` - Chunk IDs by chunks canister and principal
+	fn group_chunk_ids(chunks: Vec<(Principal, Chunk)>) -> HashMap<(Principal, Principal), Vec<u32>> {
+		let mut chunk_ids: HashMap<(Principal, Principal), Vec<u32>> = HashMap::new();
+
+		for (user_principal, chunk) in chunks {
+			chunk_ids.entry((chunk.canister, chunk.owner.unwrap_or(user_principal))).or_default().push(chunk.id);
+		}
+
+		chunk_ids
+	}
+
+	/// Delete chunks with one call per chunks canister and principal. Failed deletions are recorded, deletions
+	/// that succeed are removed from the failed deletions.
+	///
+	/// # Arguments
+	/// - `chunk_ids` - Chunk IDs by chunks canister and principal
+	///
+	/// # Returns
+	/// - `()` - No return value
+	async fn delete_chunk_ids(chunk_ids: HashMap<(Principal, Principal), Vec<u32>>) {
+		for ((canister_principal, owner_principal), chunk_ids) in chunk_ids {
+			let result: Result<Vec<u32>, ApiError> = Self::call_result(
+				call::call(canister_principal, "delete_chunks_intercanister_call", (owner_principal, chunk_ids.clone())).await
+			);
+
+			STATE.with(|state| {
+				let mut state = state.borrow_mut();
+
+				for chunk_id in chunk_ids {
+					let key = (canister_principal, owner_principal, chunk_id);
+
+					if result.is_ok() {
+						state.failed_chunk_deletions.remove(&key);
+					} else {
+						state.failed_chunk_deletions.insert(key, ());
+					}
+				}
+			});
+		}
+	}
+
+	/// Replace the chunks of a file and keep the previous chunks as a version. Versions that exceed the maximum
+	/// number of versions are removed. Chunks of removed versions that are still used by the file or by another
+	/// version are not returned, so they won't be deleted.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `asset_id` - Asset ID
+	/// - `chunks` - New chunks of the file
+	/// - `size` - New size of the file
+	///
+	/// # Returns
+	/// - `(Asset, Vec<(Principal, Chunk)>)` - Updated asset and the chunks of removed versions by owner
+	fn replace_chunks(
+		state: &mut AssetsStore,
+		asset_id: u32,
+		chunks: Vec<Chunk>,
+		size: u32
+	) -> Option<(Asset, Vec<(Principal, Chunk)>)> {
+		let mut asset = state.assets.get(&asset_id)?;
+
+		let hash = Self::file_hash(&asset.asset_type, &chunks);
+		let previous_chunks = std::mem::replace(&mut asset.chunks, chunks);
+		let previous_hash = std::mem::replace(&mut asset.hash, hash);
+		let previous_size = asset.size;
+		let previous_updated_at = asset.updated_at;
+
+		// Mutate values
+		asset.size = size;
+		asset.updated_at = time();
+
+		Self::put_asset(state, asset.clone());
+
+		let mut versions = state.versions.get(&asset_id).unwrap_or_default();
+
+		// Folders don't have chunks to keep and uploading the same chunks again doesn't create a new version
+		let is_same_chunks =
+			previous_chunks.len() == asset.chunks.len() &&
+			previous_chunks.iter().zip(&asset.chunks).all(|(previous, chunk)| previous.id == chunk.id && previous.canister == chunk.canister);
+
+		if !previous_chunks.is_empty() && !is_same_chunks {
+			let version = versions
+				.iter()
+				.map(|asset_version| asset_version.version)
+				.max()
+				.unwrap_or_default() + 1;
+
+			versions.push(AssetVersion {
+				version,
+				size: previous_size,
+				chunks: previous_chunks,
+				created_at: previous_updated_at,
+				hash: previous_hash,
+			});
+		}
+
+		let pruned_count = versions.len().saturating_sub(MAX_ASSET_VERSIONS);
+		let pruned_versions: Vec<AssetVersion> = versions.drain(..pruned_count).collect();
+
+		let used_chunks: HashSet<(u32, Principal)> = asset.chunks
+			.iter()
+			.chain(versions.iter().flat_map(|asset_version| asset_version.chunks.iter()))
+			.map(|chunk| (chunk.id, chunk.canister))
+			.collect();
+
+		let pruned_chunks = pruned_versions
+			.into_iter()
+			.flat_map(|asset_version| asset_version.chunks)
+			.filter(|chunk| !used_chunks.contains(&(chunk.id, chunk.canister)))
+			.map(|chunk| (asset.user_id, chunk))
+			.collect();
+
+		match versions.is_empty() {
+			true => {
+				state.versions.remove(&asset_id);
+			}
+			false => state.versions.insert(asset_id, versions),
+		}
+
+		Some((asset, pruned_chunks))
+	}
+
+	/// Serve a file over HTTP by the chunks canister that stores it if the file is public, otherwise stop
+	/// serving it.
+	///
+	/// # Arguments
+	/// - `asset` - Asset to serve
+	///
+	/// # Returns
+	/// - `()` - No return value
+	async fn sync_http_asset(asset: &Asset) -> Result<(), ApiError> {
+		// Folders and NFTs don't have chunks to serve
+		let Some(chunk) = asset.chunks.first() else {
+			return Ok(());
+		};
+
+		match (&asset.asset_type, &asset.settings.privacy) {
+			(AssetType::File, Privacy::Public) => {
+				let http_asset = HttpAsset {
+					asset_id: asset.id,
+					owner: chunk.owner.unwrap_or(asset.user_id),
+					name: asset.name.clone(),
+					mime_type: asset.mime_type.clone(),
+					chunks: asset.chunks.clone(),
+				};
+
+				let _: HttpAsset = Self::call_result(call::call(chunk.canister, "set_http_asset", (http_asset,)).await)?;
+
+				// A removal that is retried must not stop serving the asset again
+				STATE.with(|state| state.borrow_mut().failed_http_removals.remove(&(chunk.canister, asset.id)));
+
+				Ok(())
+			}
+			_ => {
+				Self::remove_http_asset(asset).await;
+
+				Ok(())
+			}
+		}
+	}
+
+	/// Start serving a public file over HTTP, or stop serving an asset that isn't public. A file that can't be
+	/// served is made private, so it isn't shown as public while it isn't served.
+	///
+	/// # Arguments
+	/// - `asset` - Asset
+	///
+	/// # Returns
+	/// - `Asset` - Asset with the privacy it is served with
+	async fn publish_http_asset(asset: Asset) -> Asset {
+		match Self::sync_http_asset(&asset).await {
+			Ok(_) => asset,
+			Err(_) => Self::make_private(asset).await,
+		}
+	}
+
+	/// Make an asset private and stop serving it over HTTP, as a previous version might still be served.
+	///
+	/// # Arguments
+	/// - `asset` - Asset
+	///
+	/// # Returns
+	/// - `Asset` - Private asset
+	async fn make_private(asset: Asset) -> Asset {
+		let asset = STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			match state.assets.get(&asset.id) {
+				Some(mut current_asset) => {
+					current_asset.settings.privacy = Privacy::Private;
+					Self::put_asset(&mut state, current_asset.clone());
+
+					current_asset
+				}
+				None => asset,
+			}
+		});
+
+		Self::remove_http_asset(&asset).await;
+
+		asset
+	}
+
+	/// Stop serving a file over HTTP. Removals that fail are retried by the next purge of the trash, so an asset
+	/// that is no longer public stops being served.
+	///
+	/// # Arguments
+	/// - `asset` - Asset to stop serving
+	///
+	/// # Returns
+	/// - `()` - No return value
+	async fn remove_http_asset(asset: &Asset) {
+		if let Some(chunk) = asset.chunks.first() {
+			Self::remove_http_asset_id(chunk.canister, asset.id).await;
+		}
+	}
+
+	/// Stop serving an asset over HTTP from a chunks canister. Failed removals are recorded, removals that succeed
+	/// are removed from the failed removals.
+	///
+	/// # Arguments
+	/// - `canister_principal` - Principal of the chunks canister
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `()` - No return value
+	async fn remove_http_asset_id(canister_principal: Principal, asset_id: u32) {
+		let result: Result<u32, ApiError> = Self::call_result(
+			call::call(canister_principal, "remove_http_asset", (asset_id,)).await
+		);
+
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+			let key = (canister_principal, asset_id);
+
+			match result {
+				// Assets that are not served are not found
+				Ok(_) | Err(ApiError::NotFound(_)) => {
+					state.failed_http_removals.remove(&key);
+				}
+				Err(_) => {
+					state.failed_http_removals.insert(key, ());
+				}
+			}
+		})
+	}
+
+	/// Find the assets that other users have shared with the caller, including the children of shared folders.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<SharedAsset>` - Shared assets including the owner
+	fn find_shared_assets(state: &AssetsStore, caller_principal: &Principal) -> Vec<SharedAsset> {
+		let mut shared_assets: Vec<SharedAsset> = vec![];
+		let mut added_asset_ids: HashSet<u32> = HashSet::new();
+
+		// Get caller's shared assets
+		let shared_asset_ids: Vec<u32> = state.shared
+			.prefix_keys(caller_principal)
+			.map(|(_, asset_id)| asset_id)
+			.collect();
+
+		for shared_asset_id in shared_asset_ids {
+			let Some(shared_asset) = state.assets.get(&shared_asset_id).filter(|asset| asset.deleted_at.is_none()) else {
+				continue;
+			};
+
+			let owner_principal = shared_asset.user_id;
+
+			// Get the permission the owner granted to the caller
+			let Some(permission) = state.shared_with
+				.get(&(owner_principal, shared_asset_id))
+				.and_then(|shared_with| shared_with.into_iter().find(|user| user.principal == *caller_principal))
+				.map(|user| user.permission) else {
+				continue;
+			};
+
+			// The username of the owner is only known from the accepted invite
+			let owner_username = state.asset_invites
+				.prefix(caller_principal)
+				.map(|(_, invite)| invite)
+				.find(
+					|invite|
+						invite.asset_id == shared_asset_id &&
+						invite.invited_by_principal == owner_principal &&
+						invite.status == InviteStatus::Accepted
+				)
+				.and_then(|invite| invite.invited_by_username);
+
+			let mut asset_ids = vec![shared_asset_id];
+
+			// Add the children of a shared folder
+			if let AssetType::Folder = shared_asset.asset_type {
+				asset_ids.extend(Self::find_child_asset_ids(state, &shared_asset));
+			}
+
+			for asset_id in asset_ids {
+				// The same asset can be shared directly and through a parent folder
+				if !added_asset_ids.insert(asset_id) {
+					continue;
+				}
+
+				if let Some(asset) = state.assets.get(&asset_id).filter(|asset| asset.deleted_at.is_none()) {
+					shared_assets.push(SharedAsset {
+						owner_principal,
+						owner_username: owner_username.clone(),
+						permission,
+						asset,
+					});
+				}
+			}
+		}
+
+		shared_assets
+	}
+
+	/// Find the assets of a user.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `principal` - Principal of the user
+	///
+	/// # Returns
+	/// - `Vec<Asset>` - Assets
+	fn find_user_assets(state: &AssetsStore, principal: &Principal) -> Vec<Asset> {
+		// Look up the user's assets in the owner index instead of scanning all assets
+		state.user_assets
+			.prefix_keys(principal)
+			.filter_map(|(_, asset_id)| state.assets.get(&asset_id))
+			.collect()
+	}
+
+	/// Find the assets in a folder.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `owner_principal` - Principal of the owner of the folder
+	/// - `parent_id` - Folder ID, `None` for the root of the owner
+	///
+	/// # Returns
+	/// - `Vec<Asset>` - Assets
+	fn find_folder_assets(state: &AssetsStore, owner_principal: &Principal, parent_id: Option<u32>) -> Vec<Asset> {
+		state.folder_assets
+			.prefix_keys(&(*owner_principal, parent_id))
+			.filter_map(|(_, _, asset_id)| state.assets.get(&asset_id))
+			.collect()
+	}
+
+	/// Find the next batch of expired assets to purge from the trash. Nested children of folders count against the
+	/// batch and come before the folders, the deepest first.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `now` - Current time in nanoseconds
+	///
+	/// # Returns
+	/// - `(Vec<u32>, bool)` - Asset IDs to purge and whether the batch is full
+	fn find_purge_asset_ids(state: &AssetsStore, now: u64) -> (Vec<u32>, bool) {
+let retention = (state.trash_retention_days.unwrap_or(DEFAULT_TRASH_RETENTION_DAYS) as u64) * NANOS_PER_DAY;
+
+		// The trash index is ordered by the time of deletion, so only expired assets are read
+		let expired_asset_ids = state.trash
+			.keys()
+			.take_while(|(deleted_at, _)| deleted_at.saturating_add(retention) <= now)
+			.map(|(_, asset_id)| asset_id);
+
+		let mut purge_asset_ids: Vec<u32> = vec![];
+		let mut included_asset_ids: HashSet<u32> = HashSet::new();
+
+		for asset_id in expired_asset_ids {
+			let Some(asset) = state.assets.get(&asset_id) else {
+				continue;
+			};
+
+			// The deepest children come first, so a partly purged folder never loses the parent of an asset
+			let mut asset_ids = match asset.asset_type {
+				AssetType::Folder => Self::find_child_asset_ids(state, &asset),
+				_ => vec![],
+			};
+			asset_ids.reverse();
+			asset_ids.push(asset.id);
+
+			for asset_id in asset_ids {
+				if included_asset_ids.contains(&asset_id) {
+					continue;
+				}
+
+				if purge_asset_ids.len() == TRASH_PURGE_BATCH_SIZE {
+					return (purge_asset_ids, true);
+				}
+
+				included_asset_ids.insert(asset_id);
+				purge_asset_ids.push(asset_id);
+			}
+		}
+
+		(purge_asset_ids, false)
+	}
+
+	/// Find the IDs of all nested children of a folder by walking down the folder index.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `folder` - Folder
+	///
+	/// # Returns
+	/// - `Vec<u32>` - Asset IDs of the children
+	fn find_child_asset_ids(state: &AssetsStore, folder: &Asset) -> Vec<u32> {
+		let mut child_asset_ids: Vec<u32> = vec![];
+		let mut visited_asset_ids: HashSet<u32> = HashSet::from([folder.id]);
+		let mut parent_ids = VecDeque::from([folder.id]);
+
+		// Children are always owned by the owner of the folder
+		while let Some(parent_id) = parent_ids.pop_front() {
+			for (_, _, child_id) in state.folder_assets.prefix_keys(&(folder.user_id, Some(parent_id))) {
+				if visited_asset_ids.insert(child_id) {
+					child_asset_ids.push(child_id);
+					parent_ids.push_back(child_id);
+				}
+			}
+		}
+
+		child_asset_ids
+	}
+
+	/// Add or update an asset and update the owner, folder, search and URL indexes of the asset.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `asset` - Asset
+	///
+	/// # Returns
+	/// - `()` - No return value
+	pub fn put_asset(state: &mut AssetsStore, asset: Asset) {
+		let previous_asset = state.assets.get(&asset.id);
+
+		Self::update_indexes(state, previous_asset.as_ref(), Some(&asset));
+		state.assets.insert(asset.id, asset);
+	}
+
+	/// Remove an asset and its entries in the owner, folder, search and URL indexes.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `Option<Asset>` - Removed asset
+	fn delete_asset(state: &mut AssetsStore, asset_id: u32) -> Option<Asset> {
+		let asset = state.assets.remove(&asset_id)?;
+
+		Self::update_indexes(state, Some(&asset), None);
+
+		Some(asset)
+	}
+
+	/// Update the indexes of an asset from its previous to its new values. Only the entries of values that changed
+	/// are written.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `previous_asset` - Asset as it is indexed, `None` for a new asset
+	/// - `asset` - Asset as it should be indexed, `None` for a removed asset
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn update_indexes(state: &mut AssetsStore, previous_asset: Option<&Asset>, asset: Option<&Asset>) {
+		let Some(asset_id) = asset.or(previous_asset).map(|asset| asset.id) else {
+			return;
+		};
+
+		let previous_owner = previous_asset.map(|asset| asset.user_id);
+		let owner = asset.map(|asset| asset.user_id);
+
+		if previous_owner != owner {
+			if let Some(previous_owner) = previous_owner {
+				state.user_assets.remove(&(previous_owner, asset_id));
+			}
+
+			if let Some(owner) = owner {
+				state.user_assets.insert((owner, asset_id), ());
+			}
+		}
+
+		let previous_folder = previous_asset.map(|asset| (asset.user_id, asset.parent_id));
+		let folder = asset.map(|asset| (asset.user_id, asset.parent_id));
+
+		if previous_folder != folder {
+			if let Some((owner, parent_id)) = previous_folder {
+				state.folder_assets.remove(&(owner, parent_id, asset_id));
+			}
+
+			if let Some((owner, parent_id)) = folder {
+				state.folder_assets.insert((owner, parent_id, asset_id), ());
+			}
+		}
+
+		let previous_tokens = previous_asset.map(Self::asset_tokens).unwrap_or_default();
+		let tokens = asset.map(Self::asset_tokens).unwrap_or_default();
+
+		for token in previous_tokens.difference(&tokens) {
+			state.asset_tokens.remove(&(token.clone(), asset_id));
+		}
+
+		for token in tokens.difference(&previous_tokens) {
+			state.asset_tokens.insert((token.clone(), asset_id), ());
+		}
+
+		let previous_deleted_at = previous_asset.and_then(|asset| asset.deleted_at);
+		let deleted_at = asset.and_then(|asset| asset.deleted_at);
+
+		if previous_deleted_at != deleted_at {
+			if let Some(previous_deleted_at) = previous_deleted_at {
+				state.trash.remove(&(previous_deleted_at, asset_id));
+			}
+
+			if let Some(deleted_at) = deleted_at {
+				state.trash.insert((deleted_at, asset_id), ());
+			}
+		}
+
+		let previous_url = previous_asset.and_then(|asset| asset.settings.url.clone());
+		let url = asset.and_then(|asset| asset.settings.url.clone());
+
+		if previous_url != url {
+			// The URL slug might already be taken over by another asset
+			if let Some(previous_url) = previous_url.filter(|previous_url| state.urls.get(previous_url) == Some(asset_id)) {
+				state.urls.remove(&previous_url);
+			}
+
+			if let Some(url) = url {
+				state.urls.insert(url, asset_id);
+			}
+		}
+	}
+
+	/// Split a text into lowercase words for the search index. Words are cut to the maximum length of a word in
+	/// the index.
+	///
+	/// # Arguments
+	/// - `text` - Text to split
+	///
+	/// # Returns
+	/// - `Vec<String>` - Words
+	fn tokenize(text: &str) -> Vec<String> {
+		text
+			.split(|c: char| !c.is_alphanumeric())
+			.filter(|token| !token.is_empty())
+			.map(|token| token.to_lowercase().chars().take(MAX_TOKEN_LENGTH).collect())
+			.collect()
+	}
+
+	/// Get the hash of a file from the hashes of its chunks.
+	///
+	/// # Arguments
+	/// - `asset_type` - Type of the asset
+	/// - `chunks` - Chunks of the asset
+	///
+	/// # Returns
+	/// - `Option<Vec<u8>>` - SHA-256 hash, `None` for folders and NFTs or if a chunk has no hash
+	fn file_hash(asset_type: &AssetType, chunks: &[Chunk]) -> Option<Vec<u8>> {
+		match asset_type {
+			AssetType::File => hash_file(chunks),
+			_ => None,
+		}
+	}
+
+	/// Get the words of the name and extension of an asset for the search index.
+	///
+	/// # Arguments
+	/// - `asset` - Asset
+	///
+	/// # Returns
+	/// - `BTreeSet<String>` - Words
+	fn asset_tokens(asset: &Asset) -> BTreeSet<String> {
+		Self::tokenize(&asset.name).into_iter().chain(Self::tokenize(&asset.extension)).collect()
+	}
+
+	/// Check if an asset matches the filters of a search.
+	///
+	/// # Arguments
+	/// - `asset` - Asset
+	/// - `search` - Filters
+	///
+	/// # Returns
+	/// - `bool` - True if the asset matches all filters
+	fn matches_search(asset: &Asset, search: &SearchAssets) -> bool {
+		search.mime_type.as_ref().is_none_or(|mime_type| asset.mime_type.starts_with(mime_type.as_str())) &&
+			search.min_size.is_none_or(|min_size| asset.size >= min_size) &&
+			search.max_size.is_none_or(|max_size| asset.size <= max_size) &&
+			search.updated_after.is_none_or(|updated_after| asset.updated_at >= updated_after) &&
+			search.updated_before.is_none_or(|updated_before| asset.updated_at <= updated_before) &&
+			search.is_favorite.is_none_or(|is_favorite| asset.is_favorite == is_favorite) &&
+			search.asset_type.as_ref().is_none_or(|asset_type| discriminant(asset_type) == discriminant(&asset.asset_type)) &&
+			search.tag.as_ref().is_none_or(|tag| Self::has_tag(asset, tag))
+	}
+
+	/// Check if an asset has a tag.
+	///
+	/// # Arguments
+	/// - `asset` - Asset
+	/// - `tag` - Tag
+	///
+	/// # Returns
+	/// - `bool` - True if the asset has the tag
+	fn has_tag(asset: &Asset, tag: &str) -> bool {
+		asset.tags.as_ref().is_some_and(|tags| tags.iter().any(|asset_tag| asset_tag == tag))
+	}
+
+	/// Check if tags are valid and remove surrounding whitespace and duplicates.
+	///
+	/// # Arguments
+	/// - `tags` - Tags
+	///
+	/// # Returns
+	/// - `Vec<String>` - Valid tags
+	fn check_tags(tags: Vec<String>) -> Result<Vec<String>, ApiError> {
+		let mut checked_tags: Vec<String> = vec![];
+
+		for tag in tags {
+			let tag = tag.trim().to_string();
+
+			if tag.is_empty() || tag.chars().count() > MAX_TAG_LENGTH {
+				return Err(ApiError::BadRequest("INVALID_TAG".to_string()));
+			}
+
+			if !checked_tags.contains(&tag) {
+				checked_tags.push(tag);
+			}
+		}
+
+		Ok(checked_tags)
+	}
+
+	/// Sort assets. Assets that are equal are sorted by ID, so pages don't overlap.
+	///
+	/// # Arguments
+	/// - `assets` - Assets to sort
+	/// - `sort` - Order of the assets, by name if not given
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn sort_assets(assets: &mut [Asset], sort: Option<AssetSort>) {
+		let sort = sort.unwrap_or(AssetSort { sort_by: SortBy::Name, order: SortOrder::Ascending });
+
+		assets.sort_by(|a, b| {
+			let ordering = match sort.sort_by {
+				SortBy::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+				SortBy::Size => a.size.cmp(&b.size),
+				SortBy::CreatedAt => a.created_at.cmp(&b.created_at),
+				SortBy::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+			};
+
+			let ordering = match ordering {
+				Ordering::Equal => a.id.cmp(&b.id),
+				ordering => ordering,
+			};
+
+			match sort.order {
+				SortOrder::Ascending => ordering,
+				SortOrder::Descending => ordering.reverse(),
+			}
+		});
+	}
+
+	/// Get a page of sorted assets.
+	///
+	/// # Arguments
+	/// - `assets` - Sorted assets
+	/// - `cursor` - Position of the first asset of the page
+	/// - `limit` - Maximum number of assets to return
+	///
+	/// # Returns
+	/// - `AssetPage` - Assets of the page, the total number of assets and the cursor of the next page
+	fn paginate_assets(assets: &[Asset], cursor: Option<u32>, limit: Option<u32>) -> AssetPage {
+		let total = assets.len() as u32;
+		let start = cursor.unwrap_or_default().min(total);
+		let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+		let end = start.saturating_add(limit).min(total);
+
+		AssetPage {
+			assets: assets[start as usize..end as usize].to_vec(),
+			total,
+			next_cursor: Some(end).filter(|end| *end < total),
+		}
+	}
+
+	/// Find an asset owned by the caller.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `caller_principal` - Principal of the caller
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `Asset` - Owned asset
+	fn find_owned_asset(state: &AssetsStore, caller_principal: &Principal, asset_id: u32) -> Result<Asset, ApiError> {
+		let is_owner = state.user_assets.contains_key(&(*caller_principal, asset_id));
+
+		state.assets
+			.get(&asset_id)
+			.filter(|_| is_owner)
+			.ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))
+	}
+
+	/// Check if the caller has the required permission on an asset. Owners have full access to their assets,
+	/// other users need a share on the asset or one of its parent folders.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `caller_principal` - Principal of the caller
+	/// - `asset_id` - Asset ID
+	/// - `permission` - Required permission
+	///
+	/// # Returns
+	/// - `Asset` - Asset the caller has access to
+	fn check_permission(
+		state: &AssetsStore,
+		caller_principal: &Principal,
+		asset_id: u32,
+		permission: Permission
+	) -> Result<Asset, ApiError> {
+		// Assets in the trash can only be restored
+		let asset = state.assets
+			.get(&asset_id)
+			.filter(|asset| asset.deleted_at.is_none())
+			.ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))?;
+
+		if asset.user_id == *caller_principal {
+			return Ok(asset);
+		}
+
+		match Self::find_shared_permission(state, caller_principal, &asset) {
+			Some(shared_permission) if shared_permission >= permission => Ok(asset),
+			Some(_) => Err(ApiError::Unauthorized("UNAUTHORIZED".to_string())),
+			None => Err(ApiError::NotFound("ASSET_NOT_FOUND".to_string())),
+		}
+	}
+
+	/// Find the highest permission the owner of an asset granted to the caller on the asset or one of its parent folders.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `caller_principal` - Principal of the caller
+	/// - `asset` - Asset
+	///
+	/// # Returns
+	/// - `Option<Permission>` - Permission of the caller
+	fn find_shared_permission(state: &AssetsStore, caller_principal: &Principal, asset: &Asset) -> Option<Permission> {
+		let mut permission: Option<Permission> = None;
+		let mut visited_asset_ids: HashSet<u32> = HashSet::new();
+		let mut current_asset_id = Some(asset.id);
+
+		while let Some(asset_id) = current_asset_id {
+			if !visited_asset_ids.insert(asset_id) {
+				break;
+			}
+
+			let shared_permission = state.shared_with
+				.get(&(asset.user_id, asset_id))
+				.and_then(|shared_with| shared_with.into_iter().find(|user| user.principal == *caller_principal))
+				.map(|user| user.permission);
+
+			permission = permission.max(shared_permission);
+			current_asset_id = state.assets.get(&asset_id).and_then(|asset| asset.parent_id);
+		}
+
+		permission
+	}
+
+	/// Check if the caller is allowed to move an asset to a new parent. The new parent has to be a folder of the
+	/// owner of the asset and can't be the asset itself or one of its children. Non-owners can only move assets
+	/// within folders they are allowed to edit.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `caller_principal` - Principal of the caller
+	/// - `asset` - Asset to move
+	/// - `parent_id` - New parent ID
+	/// - `moved_parent_ids` - New parent IDs of assets that are moved in the same batch
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn check_move(
+		state: &AssetsStore,
+		caller_principal: &Principal,
+		asset: &Asset,
+		parent_id: Option<u32>,
+		moved_parent_ids: &HashMap<u32, Option<u32>>
+	) -> Result<(), ApiError> {
+		match parent_id {
+			Some(parent_id) => {
+				if parent_id == asset.id {
+					return Err(ApiError::BadRequest("CANNOT_MOVE_INTO_ITSELF".to_string()));
+				}
+
+				let parent = Self::check_parent(state, caller_principal, parent_id)?;
+
+				// Assets can't be moved into the folders of another owner
+				if parent.user_id != asset.user_id {
+					return Err(ApiError::Unauthorized("PARENT_NOT_OWNED".to_string()));
+				}
+
+				if Self::is_in_subtree(state, asset.id, parent_id, moved_parent_ids) {
+					return Err(ApiError::BadRequest("CANNOT_MOVE_INTO_CHILD".to_string()));
+				}
+
+				Ok(())
+			}
+			// Only the owner can move assets to the root
+			None if asset.user_id == *caller_principal => Ok(()),
+			None => Err(ApiError::Unauthorized("UNAUTHORIZED".to_string())),
+		}
+	}
+
+	/// Check if the caller is allowed to copy an asset to a parent. The parent has to be a folder the caller is
+	/// allowed to edit and can't be the asset itself or one of its children.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `caller_principal` - Principal of the caller
+	/// - `copy_asset` - Asset to copy and the parent to copy it to
+	///
+	/// # Returns
+	/// - `Principal` - Owner of the copy
+	fn check_copy(state: &AssetsStore, caller_principal: &Principal, copy_asset: &CopyAsset) -> Result<Principal, ApiError> {
+		Self::check_permission(state, caller_principal, copy_asset.id, Permission::Viewer)?;
+
+		match copy_asset.parent_id {
+			Some(parent_id) => {
+				if parent_id == copy_asset.id {
+					return Err(ApiError::BadRequest("CANNOT_COPY_INTO_ITSELF".to_string()));
+				}
+
+				let parent = Self::check_parent(state, caller_principal, parent_id)?;
+
+				if Self::is_in_subtree(state, copy_asset.id, parent_id, &HashMap::new()) {
+					return Err(ApiError::BadRequest("CANNOT_COPY_INTO_CHILD".to_string()));
+				}
+
+				// Copies belong to the owner of the folder they are copied to
+				Ok(parent.user_id)
+			}
+			None => Ok(*caller_principal),
+		}
+	}
+
+	/// Check if the caller is allowed to add assets to a parent folder.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `caller_principal` - Principal of the caller
+	/// - `parent_id` - Parent ID
+	///
+	/// # Returns
+	/// - `Asset` - Parent folder
+	fn check_parent(state: &AssetsStore, caller_principal: &Principal, parent_id: u32) -> Result<Asset, ApiError> {
+		let parent = Self::check_permission(state, caller_principal, parent_id, Permission::Editor).map_err(|err| {
+			match err {
+				ApiError::NotFound(_) => ApiError::NotFound("PARENT_NOT_FOUND".to_string()),
+				err => err,
+			}
+		})?;
+
+		if parent.asset_type != AssetType::Folder {
+			return Err(ApiError::BadRequest("PARENT_NOT_A_FOLDER".to_string()));
+		}
+
+		Ok(parent)
+	}
+
+	/// Check if an asset is one of the parents of another asset by walking up from that asset.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `ancestor_id` - Asset ID of the possible parent
+	/// - `asset_id` - Asset ID to walk up from
+	/// - `moved_parent_ids` - New parent IDs of assets that are moved but not yet updated in the store
+	///
+	/// # Returns
+	/// - `bool` - True if the asset is the ancestor itself or one of its children
+	fn is_in_subtree(
+		state: &AssetsStore,
+		ancestor_id: u32,
+		asset_id: u32,
+		moved_parent_ids: &HashMap<u32, Option<u32>>
+	) -> bool {
+		let mut visited_asset_ids: HashSet<u32> = HashSet::new();
+		let mut current_asset_id = Some(asset_id);
+
+		while let Some(asset_id) = current_asset_id {
+			if asset_id == ancestor_id {
+				return true;
+			}
+
+			if !visited_asset_ids.insert(asset_id) {
+				return false;
+			}
+
+			current_asset_id = match moved_parent_ids.get(&asset_id) {
+				Some(moved_parent_id) => *moved_parent_id,
+				None => state.assets.get(&asset_id).and_then(|asset| asset.parent_id),
+			};
+		}
+
+		false
+	}
+
+	/// Get the key names are compared by. Names are unique per folder regardless of case.
+	///
+	/// # Arguments
+	/// - `name` - Name of the asset
+	/// - `extension` - Extension of the asset
+	///
+	/// # Returns
+	/// - `(String, String)` - Lowercase name and extension
+	fn name_key(name: &str, extension: &str) -> (String, String) {
+		(name.to_lowercase(), extension.to_lowercase())
+	}
+
+	/// Find an asset in a folder with the same name and extension, ignoring assets in the trash.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `folder` - Owner and parent ID of the folder
+	/// - `asset_id` - Asset ID the name is for, if the asset already exists
+	/// - `name` - Name to look for
+	/// - `extension` - Extension to look for
+	/// - `batch` - Moves and names of the same batch that are not yet applied to the store
+	///
+	/// # Returns
+	/// - `Option<u32>` - Asset ID of the asset with the same name
+	fn find_name_conflict(
+		state: &AssetsStore,
+		folder: (Principal, Option<u32>),
+		asset_id: Option<u32>,
+		name: &str,
+		extension: &str,
+		batch: &NameBatch
+	) -> Option<u32> {
+		let key = Self::name_key(name, extension);
+		let (owner_principal, parent_id) = folder;
+
+		if let Some(claimed_asset_id) = batch.claimed_names.get(&(owner_principal, parent_id, key.0.clone(), key.1.clone())) {
+			if Some(*claimed_asset_id) != asset_id {
+				return Some(*claimed_asset_id);
+			}
+		}
+
+		state.folder_assets
+			.prefix_keys(&folder)
+			.map(|(_, _, folder_asset_id)| folder_asset_id)
+			// Assets that are moved out of the folder in the same batch no longer conflict
+			.filter(|folder_asset_id| Some(*folder_asset_id) != asset_id && !batch.moved_parent_ids.contains_key(folder_asset_id))
+			.filter_map(|folder_asset_id| state.assets.get(&folder_asset_id))
+			.find(|asset| asset.deleted_at.is_none() && Self::name_key(&asset.name, &asset.extension) == key)
+			.map(|asset| asset.id)
+	}
+
+	/// Resolve the name of an asset in a folder according to the conflict policy.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `folder` - Owner and parent ID of the folder
+	/// - `asset_id` - Asset ID the name is for, if the asset already exists
+	/// - `name` - Requested name
+	/// - `extension` - Extension of the asset
+	/// - `on_conflict` - Conflict policy, fails by default
+	/// - `batch` - Moves and names of the same batch that are not yet applied to the store
+	///
+	/// # Returns
+	/// - `(String, Option<u32>)` - Name to use and the asset ID of the asset to replace
+	fn resolve_name(
+		state: &AssetsStore,
+		folder: (Principal, Option<u32>),
+		asset_id: Option<u32>,
+		name: &str,
+		extension: &str,
+		on_conflict: Option<NameConflict>,
+		batch: &NameBatch
+	) -> Result<(String, Option<u32>), ApiError> {
+		let Some(conflict_asset_id) = Self::find_name_conflict(state, folder, asset_id, name, extension, batch) else {
+			return Ok((name.to_string(), None));
+		};
+
+		let already_exists = ApiError::AlreadyExists(format!("NAME_ALREADY_EXISTS:{}", conflict_asset_id));
+
+		match on_conflict.unwrap_or(NameConflict::Fail) {
+			NameConflict::Fail => Err(already_exists),
+			NameConflict::Rename => {
+				let mut number = 1;
+
+				loop {
+					let numbered_name = format!("{} ({})", name, number);
+
+					if Self::find_name_conflict(state, folder, asset_id, &numbered_name, extension, batch).is_none() {
+						return Ok((numbered_name, None));
+					}
+
+					number += 1;
+				}
+			}
+			NameConflict::Replace => {
+				// Assets of the same batch can't replace each other
+				let (name_key, extension_key) = Self::name_key(name, extension);
+
+				if batch.claimed_names.contains_key(&(folder.0, folder.1, name_key, extension_key)) {
+					return Err(already_exists);
+				}
+
+				Ok((name.to_string(), Some(conflict_asset_id)))
+			}
+		}
+	}
+
+	/// Check if a URL slug is valid and not used by another asset.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `url` - URL slug
+	/// - `asset_id` - Asset ID the URL slug is for, if the asset already exists
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn check_url(state: &AssetsStore, url: &str, asset_id: Option<u32>) -> Result<(), ApiError> {
+		let is_valid =
+			!url.is_empty() &&
+			url.len() <= MAX_URL_LENGTH &&
+			url.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+		if !is_valid {
+			return Err(ApiError::BadRequest("INVALID_URL".to_string()));
+		}
+
+		match state.urls.get(&url.to_string()) {
+			Some(id) if Some(id) != asset_id => Err(ApiError::AlreadyExists("URL_ALREADY_EXISTS".to_string())),
+			_ => Ok(()),
+		}
+	}
+
+	/// Cancel the pending invites of a user for an asset.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `asset_id` - Asset ID
+	/// - `principal` - Principal of the invited user
+	///
+	/// # Returns
+	/// - `bool` - Whether an invite was cancelled
+	fn cancel_invites(state: &mut AssetsStore, asset_id: u32, principal: Principal) -> bool {
+		let pending_invites: Vec<Invite> = state.asset_invite_ids
+			.prefix_keys(&(asset_id, principal))
+			.filter_map(|(_, _, invite_id)| state.asset_invites.get(&(principal, invite_id)))
+			.filter(|invite| invite.status == InviteStatus::Pending)
+			.collect();
+
+		for invite in &pending_invites {
+			state.asset_invites.insert((principal, invite.id), Invite {
+				status: InviteStatus::Cancelled,
+				..invite.clone()
+			});
+		}
+
+		!pending_invites.is_empty()
+	}
+
+	/// Find a pending invite of the caller.
+	///
+	/// # Arguments
+	/// - `state` - Assets store
+	/// - `caller_principal` - Principal of the caller
+	/// - `invite_id` - Invite ID
+	///
+	/// # Returns
+	/// - `Invite` - Pending invite
+	fn find_pending_invite(state: &AssetsStore, caller_principal: &Principal, invite_id: u32) -> Result<Invite, ApiError> {
+		let invite = state.asset_invites
+			.get(&(*caller_principal, invite_id))
+			.filter(|invite| invite.status == InviteStatus::Pending)
+			.ok_or(ApiError::NotFound("INVITE_NOT_FOUND".to_string()))?;
+
+		if Self::is_expired(invite.expires_at) {
+			return Err(ApiError::BadRequest("INVITE_EXPIRED".to_string()));
+		}
+
+		Ok(invite)
+	}
+
+	/// Check if an expiry timestamp has passed.
+	///
+	/// # Arguments
+	/// - `expires_at` - Expiry timestamp in nanoseconds
+	///
+	/// # Returns
+	/// - `bool` - True if expired
+	fn is_expired(expires_at: Option<u64>) -> bool {
+		expires_at.is_some_and(|expires_at| expires_at <= time())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn owner() -> Principal {
+		Principal::from_slice(&[1])
+	}
+
+	fn add_asset(state: &mut AssetsStore, id: u32, parent_id: Option<u32>, asset_type: AssetType, name: &str) {
+		AssetsStore::put_asset(state, Asset {
+			id,
+			user_id: owner(),
+			parent_id,
+			asset_type,
+			name: name.to_string(),
+			..Asset::default()
+		});
+	}
+
+	// Folders 1 > 2 > 3, folder 4 and file 5 in the root
+	fn move_store() -> AssetsStore {
+		let mut state = AssetsStore::default();
+
+		add_asset(&mut state, 1, None, AssetType::Folder, "1");
+		add_asset(&mut state, 2, Some(1), AssetType::Folder, "2");
+		add_asset(&mut state, 3, Some(2), AssetType::Folder, "3");
+		add_asset(&mut state, 4, None, AssetType::Folder, "4");
+		add_asset(&mut state, 5, None, AssetType::File, "5");
+
+		state
+	}
+
+	fn move_asset(
+		state: &AssetsStore,
+		asset_id: u32,
+		parent_id: Option<u32>,
+		moved_parent_ids: &[(u32, Option<u32>)]
+	) -> Result<(), ApiError> {
+		let asset = state.assets.get(&asset_id).unwrap();
+
+		AssetsStore::check_move(state, &owner(), &asset, parent_id, &moved_parent_ids.iter().copied().collect())
+	}
+
+	#[test]
+	fn check_move() {
+		let state = move_store();
+
+		assert!(move_asset(&state, 3, Some(4), &[]).is_ok());
+		assert!(move_asset(&state, 2, None, &[]).is_ok());
+		assert!(matches!(move_asset(&state, 1, Some(1), &[]), Err(ApiError::BadRequest(err)) if err == "CANNOT_MOVE_INTO_ITSELF"));
+		assert!(matches!(move_asset(&state, 4, Some(5), &[]), Err(ApiError::BadRequest(err)) if err == "PARENT_NOT_A_FOLDER"));
+		assert!(matches!(move_asset(&state, 4, Some(6), &[]), Err(ApiError::NotFound(err)) if err == "PARENT_NOT_FOUND"));
+	}
+
+	#[test]
+	fn check_move_detects_cycles() {
+		let state = move_store();
+
+		assert!(matches!(move_asset(&state, 1, Some(3), &[]), Err(ApiError::BadRequest(err)) if err == "CANNOT_MOVE_INTO_CHILD"));
+		assert!(matches!(move_asset(&state, 1, Some(2), &[]), Err(ApiError::BadRequest(err)) if err == "CANNOT_MOVE_INTO_CHILD"));
+
+		// Moves of the same batch are taken into account
+		assert!(move_asset(&state, 1, Some(3), &[(3, Some(4))]).is_ok());
+		assert!(matches!(move_asset(&state, 1, Some(4), &[(4, Some(2))]), Err(ApiError::BadRequest(err)) if err == "CANNOT_MOVE_INTO_CHILD"));
+	}
+
+	fn share_asset(state: &mut AssetsStore, asset_id: u32, principal: Principal, permission: Permission) {
+		let mut shared_with = state.shared_with.get(&(owner(), asset_id)).unwrap_or_default();
+		shared_with.push(SharedWith { principal, username: None, permission });
+
+		state.shared_with.insert((owner(), asset_id), shared_with);
+	}
+
+	fn has_permission(state: &AssetsStore, principal: Principal, asset_id: u32, permission: Permission) -> bool {
+		AssetsStore::check_permission(state, &principal, asset_id, permission).is_ok()
+	}
+
+	#[test]
+	fn check_permission() {
+		let mut state = move_store();
+		let (viewer, editor, co_owner) = (Principal::from_slice(&[2]), Principal::from_slice(&[3]), Principal::from_slice(&[4]));
+
+		share_asset(&mut state, 5, viewer, Permission::Viewer);
+		share_asset(&mut state, 5, editor, Permission::Editor);
+		share_asset(&mut state, 5, co_owner, Permission::CoOwner);
+
+		// Owners have full access, shares grant their permission and the permissions below it
+		assert!(has_permission(&state, owner(), 5, Permission::CoOwner));
+		assert!(has_permission(&state, viewer, 5, Permission::Viewer));
+		assert!(matches!(
+			AssetsStore::check_permission(&state, &viewer, 5, Permission::Editor),
+			Err(ApiError::Unauthorized(err)) if err == "UNAUTHORIZED"
+		));
+		assert!(has_permission(&state, editor, 5, Permission::Editor));
+		assert!(!has_permission(&state, editor, 5, Permission::CoOwner));
+		assert!(has_permission(&state, co_owner, 5, Permission::CoOwner));
+
+		// Assets that aren't shared with the caller are reported as not found
+		assert!(matches!(
+			AssetsStore::check_permission(&state, &viewer, 4, Permission::Viewer),
+			Err(ApiError::NotFound(err)) if err == "ASSET_NOT_FOUND"
+		));
+
+		// Assets in the trash can't be accessed, not even by the owner
+		let mut trashed_asset = state.assets.get(&5).unwrap();
+		trashed_asset.deleted_at = Some(1);
+		AssetsStore::put_asset(&mut state, trashed_asset);
+
+		assert!(!has_permission(&state, owner(), 5, Permission::Viewer));
+		assert!(!has_permission(&state, co_owner, 5, Permission::Viewer));
+	}
+
+	#[test]
+	fn check_permission_inherits_from_parents() {
+		let mut state = move_store();
+		let user = Principal::from_slice(&[2]);
+
+		// Viewer of folder 1 and editor of its subfolder 2, the highest permission on the path counts
+		share_asset(&mut state, 1, user, Permission::Viewer);
+		share_asset(&mut state, 2, user, Permission::Editor);
+
+		assert!(matches!(AssetsStore::find_shared_permission(&state, &user, &state.assets.get(&1).unwrap()), Some(Permission::Viewer)));
+		assert!(matches!(AssetsStore::find_shared_permission(&state, &user, &state.assets.get(&3).unwrap()), Some(Permission::Editor)));
+		assert!(has_permission(&state, user, 3, Permission::Editor));
+		assert!(!has_permission(&state, user, 1, Permission::Editor));
+		assert!(!has_permission(&state, user, 4, Permission::Viewer));
+
+		// Revoking the share of the subfolder falls back to the share of the parent folder
+		state.shared_with.remove(&(owner(), 2));
+
+		assert!(matches!(AssetsStore::find_shared_permission(&state, &user, &state.assets.get(&3).unwrap()), Some(Permission::Viewer)));
+		assert!(!has_permission(&state, user, 3, Permission::Editor));
+
+		// Revoking the share of the parent folder removes the access to all children
+		state.shared_with.remove(&(owner(), 1));
+
+		assert!(AssetsStore::find_shared_permission(&state, &user, &state.assets.get(&3).unwrap()).is_none());
+		assert!(!has_permission(&state, user, 3, Permission::Viewer));
+	}
+
+	fn name_asset(
+		state: &AssetsStore,
+		asset_id: Option<u32>,
+		name: &str,
+		on_conflict: NameConflict
+	) -> Result<(String, Option<u32>), ApiError> {
+		AssetsStore::resolve_name(state, (owner(), Some(1)), asset_id, name, "", Some(on_conflict), &NameBatch::default())
+	}
+
+	// Files "Report" and "report (1)" and a trashed file "Notes" in folder 1
+	fn name_store() -> AssetsStore {
+		let mut state = AssetsStore::default();
+
+		add_asset(&mut state, 1, None, AssetType::Folder, "1");
+		add_asset(&mut state, 2, Some(1), AssetType::File, "Report");
+		add_asset(&mut state, 3, Some(1), AssetType::File, "report (1)");
+		add_asset(&mut state, 4, Some(1), AssetType::File, "Notes");
+
+		let mut trashed_asset = state.assets.get(&4).unwrap();
+		trashed_asset.deleted_at = Some(1);
+		AssetsStore::put_asset(&mut state, trashed_asset);
+
+		state
+	}
+
+	#[test]
+	fn resolve_name() {
+		let state = name_store();
+
+		assert!(matches!(name_asset(&state, None, "Summary", NameConflict::Fail), Ok((name, None)) if name == "Summary"));
+		// Assets in the trash and the asset itself don't conflict
+		assert!(matches!(name_asset(&state, None, "notes", NameConflict::Fail), Ok((name, None)) if name == "notes"));
+		assert!(matches!(name_asset(&state, Some(2), "REPORT", NameConflict::Fail), Ok((name, None)) if name == "REPORT"));
+		// Names are compared regardless of case
+		assert!(matches!(name_asset(&state, None, "REPORT", NameConflict::Fail), Err(ApiError::AlreadyExists(err)) if err == "NAME_ALREADY_EXISTS:2"));
+		assert!(matches!(name_asset(&state, None, "REPORT", NameConflict::Rename), Ok((name, None)) if name == "REPORT (2)"));
+		assert!(matches!(name_asset(&state, None, "REPORT", NameConflict::Replace), Ok((name, Some(2))) if name == "REPORT"));
+	}
+
+	#[test]
+	fn resolve_name_in_batch() {
+		let state = name_store();
+		let mut batch = NameBatch::default();
+
+		batch.claimed_names.insert((owner(), Some(1), "summary".to_string(), String::new()), 5);
+		batch.moved_parent_ids.insert(2, None);
+
+		let name_asset = |name: &str, on_conflict: NameConflict| {
+			AssetsStore::resolve_name(&state, (owner(), Some(1)), Some(6), name, "", Some(on_conflict), &batch)
+		};
+
+		// Names claimed by the batch conflict and can't be replaced, names of assets moved away are free
+		assert!(matches!(name_asset("Summary", NameConflict::Fail), Err(ApiError::AlreadyExists(err)) if err == "NAME_ALREADY_EXISTS:5"));
+		assert!(matches!(name_asset("Summary", NameConflict::Replace), Err(ApiError::AlreadyExists(_))));
+		assert!(matches!(name_asset("Summary", NameConflict::Rename), Ok((name, None)) if name == "Summary (1)"));
+		assert!(matches!(name_asset("Report", NameConflict::Fail), Ok((name, None)) if name == "Report"));
+	}
+
+	#[test]
+	fn restore_renames_taken_names() {
+		let mut state = AssetsStore::default();
+
+		// Trashed file "a.txt" in the root and trashed folder 1 with file "b.txt", then new files with the same
+		// names in the root
+		add_asset(&mut state, 1, None, AssetType::Folder, "1");
+		add_asset(&mut state, 2, None, AssetType::File, "a");
+		add_asset(&mut state, 3, Some(1), AssetType::File, "b");
+
+		for id in [1, 2, 3] {
+			let mut trashed_asset = state.assets.get(&id).unwrap();
+			trashed_asset.extension = "txt".to_string();
+			trashed_asset.deleted_at = Some(1);
+			AssetsStore::put_asset(&mut state, trashed_asset);
+		}
+
+		add_asset(&mut state, 4, None, AssetType::File, "a");
+		add_asset(&mut state, 5, None, AssetType::File, "b");
+
+		for id in [4, 5] {
+			let mut asset = state.assets.get(&id).unwrap();
+			asset.extension = "txt".to_string();
+			AssetsStore::put_asset(&mut state, asset);
+		}
+
+		// File 3 is restored to the root, as its folder is still in the trash
+		let restored_assets = AssetsStore::restore_listed_assets(&mut state, &[2, 3], 2);
+
+		assert!(matches!(
+			restored_assets.as_slice(),
+			[a, b] if a.name == "a (1)" && b.name == "b (1)" && b.parent_id.is_none() && b.deleted_at.is_none()
+		));
+		assert!(state.assets.get(&4).is_some_and(|asset| asset.name == "a"));
+	}
+
+	#[test]
+	fn cancel_pending_invites() {
+		let mut state = AssetsStore::default();
+		let invited_principal = Principal::management_canister();
+
+		for (invite_id, status) in [(1, InviteStatus::Declined), (2, InviteStatus::Pending)] {
+			state.asset_invites.insert((invited_principal, invite_id), Invite { id: invite_id, asset_id: 1, status, ..Invite::default() });
+			state.asset_invite_ids.insert((1, invited_principal, invite_id), ());
+		}
+
+		assert!(AssetsStore::cancel_invites(&mut state, 1, invited_principal));
+		assert!(state.asset_invites.get(&(invited_principal, 1)).is_some_and(|invite| invite.status == InviteStatus::Declined));
+		assert!(state.asset_invites.get(&(invited_principal, 2)).is_some_and(|invite| invite.status == InviteStatus::Cancelled));
+
+		// Nothing is left to cancel
+		assert!(!AssetsStore::cancel_invites(&mut state, 1, invited_principal));
+	}
+
+	#[test]
+	fn purge_trash_in_batches() {
+		let mut state = AssetsStore::default();
+
+		// A trashed folder 1 with a subfolder 2 holding more files than fit in one batch, and a trashed file 3
+		add_asset(&mut state, 1, None, AssetType::Folder, "1");
+		add_asset(&mut state, 2, Some(1), AssetType::Folder, "2");
+		add_asset(&mut state, 3, None, AssetType::File, "3");
+
+		for id in 10..(10 + TRASH_PURGE_BATCH_SIZE as u32) {
+			add_asset(&mut state, id, Some(2), AssetType::File, &id.to_string());
+		}
+
+		for (id, deleted_at) in [(3, 2), (1, 1), (2, 1)] {
+			let mut trashed_asset = state.assets.get(&id).unwrap();
+			trashed_asset.deleted_at = Some(deleted_at);
+			AssetsStore::put_asset(&mut state, trashed_asset);
+		}
+
+		// Nothing expired yet
+		assert!(matches!(AssetsStore::find_purge_asset_ids(&state, 1), (asset_ids, false) if asset_ids.is_empty()));
+
+		let now = 2 + (DEFAULT_TRASH_RETENTION_DAYS as u64) * NANOS_PER_DAY;
+
+		// The files of the subfolder fill the batch, the folders stay until their children are gone
+		let (asset_ids, is_batch_full) = AssetsStore::find_purge_asset_ids(&state, now);
+		assert!(is_batch_full);
+		assert_eq!(asset_ids.len(), TRASH_PURGE_BATCH_SIZE);
+		assert!(asset_ids.iter().all(|asset_id| *asset_id >= 10));
+
+		AssetsStore::remove_listed_assets(&mut state, &asset_ids);
+
+		let (asset_ids, is_batch_full) = AssetsStore::find_purge_asset_ids(&state, now);
+		assert!(!is_batch_full);
+		assert_eq!(asset_ids, vec![2, 1, 3]);
+	}
+
+	#[test]
+	fn delete_chunks_of_editor_uploads() {
+		let mut state = AssetsStore::default();
+		let editor = Principal::from_slice(&[2]);
+		let owner_canister = Principal::from_slice(&[3]);
+		let editor_canister = Principal::from_slice(&[4]);
+
+		let chunk = |id: u32, canister: Principal, owner: Option<Principal>| Chunk {
+			id,
+			index: 0,
+			canister,
+			owner,
+			hash: None,
+		};
+
+		// A file uploaded by an editor to the folder of the owner, and a file from before owners were recorded
+		add_asset(&mut state, 1, None, AssetType::Folder, "1");
+		AssetsStore::put_asset(&mut state, Asset {
+			id: 2,
+			user_id: owner(),
+			parent_id: Some(1),
+			asset_type: AssetType::File,
+			name: "2".to_string(),
+			chunks: vec![chunk(7, editor_canister, Some(editor))],
+			..Asset::default()
+		});
+		AssetsStore::put_asset(&mut state, Asset {
+			id: 3,
+			user_id: owner(),
+			parent_id: Some(1),
+			asset_type: AssetType::File,
+			name: "3".to_string(),
+			chunks: vec![chunk(8, owner_canister, None)],
+			..Asset::default()
+		});
+
+		for id in 1..=3 {
+			let mut trashed_asset = state.assets.get(&id).unwrap();
+			trashed_asset.deleted_at = Some(1);
+			AssetsStore::put_asset(&mut state, trashed_asset);
+		}
+
+		let (_, removed_chunks) = AssetsStore::remove_assets(&mut state, &[1]);
+
+		// The blobs are deleted from the principal they are stored under, not the owner of the file
+		let chunk_ids = AssetsStore::group_chunk_ids(removed_chunks);
+		assert_eq!(chunk_ids.len(), 2);
+		assert_eq!(chunk_ids[&(editor_canister, editor)], vec![7]);
+		assert_eq!(chunk_ids[&(owner_canister, owner())], vec![8]);
+	}
 }
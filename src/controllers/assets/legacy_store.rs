@@ -0,0 +1,180 @@
+use crate::assets_store::{ AssetsStore, MAX_URL_LENGTH };
+use candid::{ CandidType, Deserialize, Principal };
+use lib::types::{ asset::{ Asset, AssetStoreState, Permission, SharedWith }, invite::{ Invite, InviteStatus } };
+use std::collections::HashMap;
+
+/// Layout of the `AssetsStore` as deployed before sharing and URL slugs were implemented. Only used to migrate
+/// the stable memory of deployed canisters in `post_upgrade`.
+#[derive(CandidType, Clone, Deserialize)]
+pub struct LegacyAssetsStore {
+	pub asset_id: u32,
+	pub assets: HashMap<u32, Asset>,
+	pub user_assets: HashMap<Principal, Vec<u32>>,
+	pub asset_invites: HashMap<Principal, LegacyInvite>,
+	pub shared: HashMap<Principal, Vec<u32>>,
+	pub shared_with: HashMap<(Principal, u32), Vec<LegacySharedWith>>,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct LegacyInvite {
+	pub invited_by_principal: Principal,
+	pub invited_by_username: Option<String>,
+	pub invited_username: Option<String>,
+	pub asset_id: u32,
+	pub status: InviteStatus,
+	pub expires_at: Option<u64>,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct LegacySharedWith {
+	pub principal: Principal,
+	pub username: Option<String>,
+}
+
+impl From<LegacyAssetsStore> for AssetsStore {
+	/// Moves the assets to stable memory and builds the indexes from them. This must only be called after the legacy
+	/// store is restored, as the memory manager overwrites the serialized store.
+	fn from(legacy_store: LegacyAssetsStore) -> Self {
+		let mut store = AssetsStore::from(AssetStoreState {
+			asset_id: legacy_store.asset_id,
+			invite_id: 0,
+			trash_retention_days: None,
+			users_canister: None,
+		});
+
+		// The owner, folder, search and URL indexes are built from the assets
+		for mut asset in legacy_store.assets.into_values() {
+			// URL slugs were stored unchecked, longer slugs don't fit in a key of the URL index and are removed
+			if asset.settings.url.as_ref().is_some_and(|url| url.len() > MAX_URL_LENGTH) {
+				asset.settings.url = None;
+			}
+
+			AssetsStore::put_asset(&mut store, asset);
+		}
+
+		// Give every existing invite its own ID
+		for (principal, legacy_invite) in legacy_store.asset_invites {
+			store.invite_id += 1;
+			let invite_id = store.invite_id;
+
+			store.asset_invite_ids.insert((legacy_invite.asset_id, principal, invite_id), ());
+			store.asset_invites.insert((principal, invite_id), Invite {
+				id: invite_id,
+				invited_by_principal: legacy_invite.invited_by_principal,
+				invited_by_username: legacy_invite.invited_by_username,
+				invited_username: legacy_invite.invited_username,
+				asset_id: legacy_invite.asset_id,
+				// Existing shares only had read access
+				permission: Permission::Viewer,
+				status: legacy_invite.status,
+				expires_at: legacy_invite.expires_at,
+			});
+		}
+
+		for (principal, asset_ids) in legacy_store.shared {
+			for asset_id in asset_ids {
+				store.shared.insert((principal, asset_id), ());
+			}
+		}
+
+		for (key, legacy_shared_with) in legacy_store.shared_with {
+			let shared_with: Vec<SharedWith> = legacy_shared_with
+				.into_iter()
+				.map(|user| SharedWith {
+					principal: user.principal,
+					username: user.username,
+					permission: Permission::Viewer,
+				})
+				.collect();
+
+			store.shared_with.insert(key, shared_with);
+		}
+
+		store
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn principal(byte: u8) -> Principal {
+		Principal::from_slice(&[byte])
+	}
+
+	fn legacy_store(assets: Vec<Asset>) -> LegacyAssetsStore {
+		let legacy_invite = |asset_id| LegacyInvite {
+			invited_by_principal: principal(1),
+			invited_by_username: None,
+			invited_username: None,
+			asset_id,
+			status: InviteStatus::Pending,
+			expires_at: None,
+		};
+
+		LegacyAssetsStore {
+			asset_id: assets.len() as u32,
+			assets: assets.into_iter().map(|asset| (asset.id, asset)).collect(),
+			user_assets: HashMap::new(),
+			asset_invites: HashMap::from([(principal(2), legacy_invite(1)), (principal(3), legacy_invite(2))]),
+			shared: HashMap::from([(principal(2), vec![1])]),
+			shared_with: HashMap::from([
+				((principal(1), 1), vec![LegacySharedWith { principal: principal(2), username: None }]),
+			]),
+		}
+	}
+
+	#[test]
+	fn legacy_store_is_moved_to_stable_maps() {
+		let folder = Asset { id: 1, user_id: principal(1), ..Asset::default() };
+
+		let mut long_url_asset = Asset {
+			id: 2,
+			user_id: principal(1),
+			parent_id: Some(1),
+			name: "Report".to_string(),
+			..Asset::default()
+		};
+		long_url_asset.settings.url = Some("a".repeat(MAX_URL_LENGTH + 1));
+
+		let mut url_asset = Asset { id: 3, user_id: principal(1), parent_id: Some(1), ..Asset::default() };
+		url_asset.settings.url = Some("notes".to_string());
+
+		let store = AssetsStore::from(legacy_store(vec![folder, long_url_asset, url_asset]));
+
+		assert_eq!(store.asset_id, 3);
+		assert_eq!(store.user_assets.prefix_keys(&principal(1)).count(), 3);
+		assert_eq!(store.folder_assets.prefix_keys(&(principal(1), Some(1u32))).count(), 2);
+		assert_eq!(
+			store.asset_tokens.prefix_keys(&"report".to_string()).collect::<Vec<_>>(),
+			vec![("report".to_string(), 2)]
+		);
+		assert_eq!(store.trash.keys().count(), 0);
+
+		// Slugs over the length limit are removed
+		assert!(store.assets.get(&2).unwrap().settings.url.is_none());
+		assert_eq!(store.urls.get(&"notes".to_string()), Some(3));
+	}
+
+	#[test]
+	fn legacy_invites_and_shares_are_migrated() {
+		let store = AssetsStore::from(legacy_store(vec![]));
+
+		// Every invite gets its own ID and existing shares only have read access
+		let mut invites: Vec<(Principal, u32)> = store.asset_invites
+			.iter()
+			.map(|((principal, invite_id), invite)| {
+				assert_eq!(invite_id, invite.id);
+				assert!(invite.permission == Permission::Viewer);
+				assert!(store.asset_invite_ids.contains_key(&(invite.asset_id, principal, invite_id)));
+				(principal, invite.asset_id)
+			})
+			.collect();
+		invites.sort();
+
+		assert_eq!(invites, vec![(principal(2), 1), (principal(3), 2)]);
+		assert_eq!(store.invite_id, 2);
+		assert!(store.shared.contains_key(&(principal(2), 1)));
+		assert!(store.shared_with.get(&(principal(1), 1)).unwrap()[0].permission == Permission::Viewer);
+	}
+}
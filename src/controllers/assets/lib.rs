@@ -1,2 +1,3 @@
 pub mod assets_controller;
 pub mod assets_store;
+pub mod legacy_store;
@@ -0,0 +1,178 @@
+use ic_certified_map::{ labeled, labeled_hash, AsHashTree, Hash, HashTree, RbTree };
+use lib::types::http::HeaderField;
+use sha2::{ Digest, Sha256 };
+
+// Expression that is certified with the responses: the request isn't certified, the status code, the body and the
+// listed headers of the response are
+pub const CERTIFICATE_EXPRESSION: &str = concat!(
+	"default_certification(ValidationArgs{certification:Certification{no_request_certification:Empty{},",
+	"response_certification:ResponseCertification{certified_response_headers:ResponseHeaderList{",
+	"headers:[\"content-type\",\"content-range\"]}}}})"
+);
+// Header of the response that holds the certificate expression
+pub const CERTIFICATE_EXPRESSION_HEADER: &str = "IC-CertificateExpression";
+// Headers of the response that are certified, the certificate expression header is always certified
+const CERTIFIED_HEADERS: [&str; 3] = ["content-type", "content-range", "ic-certificateexpression"];
+// Pseudo header of the status code of the response
+const STATUS_CODE_HEADER: &str = ":ic-cert-status";
+// Label of the subtree of a path that only matches the exact path
+pub const EXACT_PATH_LABEL: &str = "<$>";
+// Label of the hash of the request, which is empty as the request isn't certified
+const NO_REQUEST_LABEL: &[u8] = b"";
+
+/// Certified responses of one URL path, as the subtree `<$>/<expression hash>/""/<response hash>` of the path in
+/// the `http_expr` tree of the HTTP gateway protocol.
+pub struct CertifiedResponses {
+	expression_hash: Hash,
+	// Hashes of the certified responses, the values are empty leaves
+	responses: RbTree<Hash, Vec<u8>>,
+}
+
+impl Default for CertifiedResponses {
+	fn default() -> Self {
+		Self {
+			expression_hash: Sha256::digest(CERTIFICATE_EXPRESSION).into(),
+			responses: RbTree::new(),
+		}
+	}
+}
+
+impl CertifiedResponses {
+	/// Certify a response.
+	///
+	/// # Arguments
+	/// - `response_hash` - Hash of the response, see `response_hash`
+	///
+	/// # Returns
+	/// - `()` - No return value
+	pub fn insert(&mut self, response_hash: Hash) {
+		self.responses.insert(response_hash, vec![]);
+	}
+
+	/// Build the proof that a response is certified.
+	///
+	/// # Arguments
+	/// - `response_hash` - Hash of the response
+	///
+	/// # Returns
+	/// - `HashTree` - Subtree of the path with the response
+	pub fn witness(&self, response_hash: &Hash) -> HashTree<'_> {
+		self.with_labels(self.responses.witness(response_hash))
+	}
+
+	fn with_labels<'a>(&'a self, responses: HashTree<'a>) -> HashTree<'a> {
+		labeled(EXACT_PATH_LABEL.as_bytes(), labeled(&self.expression_hash, labeled(NO_REQUEST_LABEL, responses)))
+	}
+}
+
+impl AsHashTree for CertifiedResponses {
+	fn root_hash(&self) -> Hash {
+		labeled_hash(
+			EXACT_PATH_LABEL.as_bytes(),
+			&labeled_hash(&self.expression_hash, &labeled_hash(NO_REQUEST_LABEL, &self.responses.root_hash()))
+		)
+	}
+
+	fn as_hash_tree(&self) -> HashTree<'_> {
+		self.with_labels(self.responses.as_hash_tree())
+	}
+}
+
+/// Hash a response the way the HTTP gateway does when it verifies the response: the representation independent
+/// hash of the certified headers and the status code, followed by the hash of the body.
+///
+/// # Arguments
+/// - `status_code` - Status code of the response
+/// - `headers` - Headers of the response, headers that aren't certified are skipped
+/// - `body_hash` - SHA-256 hash of the body
+///
+/// # Returns
+/// - `Hash` - Hash of the response
+pub fn response_hash(status_code: u16, headers: &[HeaderField], body_hash: &Hash) -> Hash {
+	let mut field_hashes: Vec<Vec<u8>> = headers
+		.iter()
+		.map(|(name, value)| (name.to_lowercase(), value))
+		.filter(|(name, _)| CERTIFIED_HEADERS.contains(&name.as_str()))
+		.map(|(name, value)| [Sha256::digest(name), Sha256::digest(value)].concat())
+		.collect();
+
+	field_hashes.push([Sha256::digest(STATUS_CODE_HEADER), Sha256::digest(leb128(status_code as u64))].concat());
+	field_hashes.sort();
+
+	let headers_hash = Sha256::digest(field_hashes.concat());
+
+	Sha256::digest([headers_hash.as_slice(), body_hash].concat()).into()
+}
+
+/// Encode a number as unsigned LEB128, as numbers are hashed in a representation independent hash.
+///
+/// # Arguments
+/// - `value` - Number
+///
+/// # Returns
+/// - `Vec<u8>` - Encoded number
+fn leb128(mut value: u64) -> Vec<u8> {
+	let mut bytes: Vec<u8> = vec![];
+
+	loop {
+		let byte = (value & 0x7f) as u8;
+		value >>= 7;
+
+		if value == 0 {
+			bytes.push(byte);
+			return bytes;
+		}
+
+		bytes.push(byte | 0x80);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn leb128_numbers() {
+		assert_eq!(leb128(0), vec![0x00]);
+		assert_eq!(leb128(127), vec![0x7f]);
+		assert_eq!(leb128(200), vec![0xc8, 0x01]);
+		assert_eq!(leb128(624485), vec![0xe5, 0x8e, 0x26]);
+	}
+
+	#[test]
+	fn response_hash_of_certified_headers() {
+		let body_hash: Hash = Sha256::digest(b"body").into();
+		let headers = vec![
+			("Content-Type".to_string(), "text/plain".to_string()),
+			(CERTIFICATE_EXPRESSION_HEADER.to_string(), CERTIFICATE_EXPRESSION.to_string())
+		];
+
+		// The order and the case of the headers don't matter and headers that aren't certified are skipped
+		let mut other_headers: Vec<HeaderField> = headers
+			.iter()
+			.rev()
+			.map(|(name, value)| (name.to_uppercase(), value.clone()))
+			.collect();
+		other_headers.push(("Accept-Ranges".to_string(), "bytes".to_string()));
+
+		assert_eq!(response_hash(200, &headers, &body_hash), response_hash(200, &other_headers, &body_hash));
+		assert_ne!(response_hash(200, &headers, &body_hash), response_hash(206, &headers, &body_hash));
+		assert_ne!(response_hash(200, &headers, &body_hash), response_hash(200, &headers[..1], &body_hash));
+	}
+
+	#[test]
+	fn witness_proves_the_response() {
+		let mut certified_responses = CertifiedResponses::default();
+		let response_hashes: Vec<Hash> = (0..4u8).map(|index| Sha256::digest([index]).into()).collect();
+
+		for response_hash in &response_hashes {
+			certified_responses.insert(*response_hash);
+		}
+
+		assert_eq!(certified_responses.as_hash_tree().reconstruct(), certified_responses.root_hash());
+
+		for response_hash in &response_hashes {
+			assert_eq!(certified_responses.witness(response_hash).reconstruct(), certified_responses.root_hash());
+		}
+	}
+}
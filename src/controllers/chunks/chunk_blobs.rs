@@ -0,0 +1,232 @@
+use candid::Principal;
+use ic_stable_structures::{ memory_manager::MemoryId, storable::Blob, BoundedStorable, StableBTreeMap, Storable };
+use lib::{ stable_memory::{ get_memory, Memory }, utils::hash_chunk };
+use std::{ borrow::Cow, cell::RefCell, ops::RangeInclusive };
+
+// Memory the blobs of the chunks are stored in
+const CHUNKS_MEMORY_ID: MemoryId = MemoryId::new(1);
+// Memory of the index of the chunks, so they can be listed without reading their blobs
+const CHUNK_KEYS_MEMORY_ID: MemoryId = MemoryId::new(2);
+// Memory of the hashes of the blobs, so the assets canister can check a file without trusting the client
+const CHUNK_HASHES_MEMORY_ID: MemoryId = MemoryId::new(3);
+// Values of a stable map have a maximum size, so blobs are split into pages of this size
+const BLOB_PAGE_SIZE: usize = 16 * 1024;
+// Maximum length of a principal in bytes
+const MAX_PRINCIPAL_SIZE: usize = 29;
+
+type BlobPage = Blob<BLOB_PAGE_SIZE>;
+type BlobHash = Blob<32>;
+
+thread_local! {
+	// Pages of the blobs of all chunks. Kept in stable memory, so they don't have to be serialized on upgrades
+	static CHUNK_BLOBS: RefCell<StableBTreeMap<BlobKey, BlobPage, Memory>> = RefCell::new(
+		StableBTreeMap::init(get_memory(CHUNKS_MEMORY_ID))
+	);
+	// Keys of the first pages of all blobs, one entry per chunk
+	static CHUNK_KEYS: RefCell<StableBTreeMap<BlobKey, (), Memory>> = RefCell::new(
+		StableBTreeMap::init(get_memory(CHUNK_KEYS_MEMORY_ID))
+	);
+	// SHA-256 hashes of the blobs, keyed by the first page of the blob
+	static CHUNK_HASHES: RefCell<StableBTreeMap<BlobKey, BlobHash, Memory>> = RefCell::new(
+		StableBTreeMap::init(get_memory(CHUNK_HASHES_MEMORY_ID))
+	);
+}
+
+/// Key of a page of a blob. Pages of the same chunk are next to each other, ordered by their page number.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct BlobKey {
+	chunk_id: u32,
+	principal: Principal,
+	page: u32,
+}
+
+impl Storable for BlobKey {
+	fn to_bytes(&self) -> Cow<'_, [u8]> {
+		let principal = self.principal.as_slice();
+		let mut bytes = Vec::with_capacity(Self::MAX_SIZE as usize);
+
+		bytes.extend_from_slice(&self.chunk_id.to_be_bytes());
+		bytes.push(principal.len() as u8);
+		bytes.extend_from_slice(principal);
+		bytes.resize(4 + 1 + MAX_PRINCIPAL_SIZE, 0);
+		bytes.extend_from_slice(&self.page.to_be_bytes());
+
+		Cow::Owned(bytes)
+	}
+
+	fn from_bytes(bytes: Cow<[u8]>) -> Self {
+		let principal_size = bytes[4] as usize;
+		let page_offset = 4 + 1 + MAX_PRINCIPAL_SIZE;
+
+		Self {
+			chunk_id: u32::from_be_bytes(bytes[0..4].try_into().unwrap()),
+			principal: Principal::from_slice(&bytes[5..5 + principal_size]),
+			page: u32::from_be_bytes(bytes[page_offset..page_offset + 4].try_into().unwrap()),
+		}
+	}
+}
+
+impl BoundedStorable for BlobKey {
+	const MAX_SIZE: u32 = (4 + 1 + MAX_PRINCIPAL_SIZE + 4) as u32;
+	const IS_FIXED_SIZE: bool = true;
+}
+
+/// Blobs of the chunks, stored in stable memory.
+pub struct ChunkBlobs;
+
+impl ChunkBlobs {
+	/// Get the blob of a chunk.
+	///
+	/// # Arguments
+	/// - `chunk_id` - Chunk ID
+	/// - `principal` - Principal the chunk belongs to
+	///
+	/// # Returns
+	/// - `Option<Vec<u8>>` - Blob
+	pub fn get(chunk_id: u32, principal: Principal) -> Option<Vec<u8>> {
+		CHUNK_BLOBS.with(|chunk_blobs| {
+			let chunk_blobs = chunk_blobs.borrow();
+			let mut pages = chunk_blobs.range(Self::page_range(chunk_id, principal)).peekable();
+
+			pages.peek()?;
+
+			Some(pages.flat_map(|(_, page)| page.as_slice().to_vec()).collect())
+		})
+	}
+
+	/// Get the length of the blob of a chunk. Only the last page of the blob is read.
+	///
+	/// # Arguments
+	/// - `chunk_id` - Chunk ID
+	/// - `principal` - Principal the chunk belongs to
+	///
+	/// # Returns
+	/// - `Option<usize>` - Length of the blob in bytes
+	pub fn len(chunk_id: u32, principal: Principal) -> Option<usize> {
+		CHUNK_BLOBS.with(|chunk_blobs| {
+			let chunk_blobs = chunk_blobs.borrow();
+
+			let (key, page) = chunk_blobs
+				.iter_upper_bound(&Self::page_key(chunk_id, principal, u32::MAX))
+				.next()
+				.filter(|(key, _)| key.chunk_id == chunk_id && key.principal == principal)?;
+
+			Some((key.page as usize) * BLOB_PAGE_SIZE + page.len())
+		})
+	}
+
+	/// Get the SHA-256 hash of the blob of a chunk. Blobs stored before hashes were recorded are hashed on the fly.
+	///
+	/// # Arguments
+	/// - `chunk_id` - Chunk ID
+	/// - `principal` - Principal the chunk belongs to
+	///
+	/// # Returns
+	/// - `Option<Vec<u8>>` - SHA-256 hash
+	pub fn hash(chunk_id: u32, principal: Principal) -> Option<Vec<u8>> {
+		match CHUNK_HASHES.with(|chunk_hashes| chunk_hashes.borrow().get(&Self::page_key(chunk_id, principal, 0))) {
+			Some(hash) => Some(hash.as_slice().to_vec()),
+			None => Self::get(chunk_id, principal).map(|blob| hash_chunk(&blob)),
+		}
+	}
+
+	/// Add the blob of a chunk. An existing blob of the chunk is replaced.
+	///
+	/// # Arguments
+	/// - `chunk_id` - Chunk ID
+	/// - `principal` - Principal the chunk belongs to
+	/// - `blob` - Blob
+	///
+	/// # Returns
+	/// - `()` - No return value
+	pub fn insert(chunk_id: u32, principal: Principal, blob: &[u8]) {
+		Self::remove(chunk_id, principal);
+
+		CHUNK_BLOBS.with(|chunk_blobs| {
+			let mut chunk_blobs = chunk_blobs.borrow_mut();
+
+			// An empty blob still gets a page, so the chunk exists
+			let pages: Vec<&[u8]> = match blob.is_empty() {
+				true => vec![blob],
+				false => blob.chunks(BLOB_PAGE_SIZE).collect(),
+			};
+
+			for (page, bytes) in pages.into_iter().enumerate() {
+				let blob_page = BlobPage::try_from(bytes).expect("Page exceeds the page size");
+				chunk_blobs.insert(Self::page_key(chunk_id, principal, page as u32), blob_page);
+			}
+		});
+
+		CHUNK_KEYS.with(|chunk_keys| chunk_keys.borrow_mut().insert(Self::page_key(chunk_id, principal, 0), ()));
+
+		let hash = BlobHash::try_from(hash_chunk(blob).as_slice()).expect("Hash exceeds the hash size");
+		CHUNK_HASHES.with(|chunk_hashes| chunk_hashes.borrow_mut().insert(Self::page_key(chunk_id, principal, 0), hash));
+	}
+
+	/// Remove the blob of a chunk.
+	///
+	/// # Arguments
+	/// - `chunk_id` - Chunk ID
+	/// - `principal` - Principal the chunk belongs to
+	///
+	/// # Returns
+	/// - `bool` - True if the blob existed
+	pub fn remove(chunk_id: u32, principal: Principal) -> bool {
+		CHUNK_BLOBS.with(|chunk_blobs| {
+			let mut chunk_blobs = chunk_blobs.borrow_mut();
+
+			let keys: Vec<BlobKey> = chunk_blobs
+				.range(Self::page_range(chunk_id, principal))
+				.map(|(key, _)| key)
+				.collect();
+
+			for key in &keys {
+				chunk_blobs.remove(key);
+			}
+
+			CHUNK_KEYS.with(|chunk_keys| chunk_keys.borrow_mut().remove(&Self::page_key(chunk_id, principal, 0)));
+			CHUNK_HASHES.with(|chunk_hashes| chunk_hashes.borrow_mut().remove(&Self::page_key(chunk_id, principal, 0)));
+
+			!keys.is_empty()
+		})
+	}
+
+	/// Get the keys of all chunks.
+	///
+	/// # Returns
+	/// - `Vec<(u32, Principal)>` - Chunk IDs and the principals they belong to
+	pub fn keys() -> Vec<(u32, Principal)> {
+		CHUNK_KEYS.with(|chunk_keys| {
+			chunk_keys
+				.borrow()
+				.iter()
+				.map(|(key, _)| (key.chunk_id, key.principal))
+				.collect()
+		})
+	}
+
+	/// Get the range of the keys of all pages of a blob.
+	///
+	/// # Arguments
+	/// - `chunk_id` - Chunk ID
+	/// - `principal` - Principal the chunk belongs to
+	///
+	/// # Returns
+	/// - `RangeInclusive<BlobKey>` - Keys of the pages
+	fn page_range(chunk_id: u32, principal: Principal) -> RangeInclusive<BlobKey> {
+		Self::page_key(chunk_id, principal, 0)..=Self::page_key(chunk_id, principal, u32::MAX)
+	}
+
+	/// Create the key of a page of a blob.
+	///
+	/// # Arguments
+	/// - `chunk_id` - Chunk ID
+	/// - `principal` - Principal the chunk belongs to
+	/// - `page` - Page number
+	///
+	/// # Returns
+	/// - `BlobKey` - Key of the page
+	fn page_key(chunk_id: u32, principal: Principal, page: u32) -> BlobKey {
+		BlobKey { chunk_id, principal, page }
+	}
+}
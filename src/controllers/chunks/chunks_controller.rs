@@ -3,22 +3,42 @@ use candid::{ candid_method, Principal };
 use ic_cdk::{ caller, storage };
 use ic_cdk_macros::{ post_upgrade, pre_upgrade, query, update, init };
 use lib::{
-	types::{ api_error::{ ApiError }, chunk::{ Chunk, PostChunk, ChunkStoreState } },
+	types::{
+		api_error::{ ApiError },
+		chunk::{ Chunk, PostChunk, ChunkStoreState, UploadSession, UploadStatus },
+		http::{ HttpAsset, HttpRequest, HttpResponse, StreamingCallbackHttpResponse, StreamingCallbackToken },
+	},
+	stable_memory,
 	utils::{ validate_anonymous, validate_admin },
 };
-use crate::chunks_store::{ ChunksStore, STATE };
+use crate::{
+	chunk_blobs::ChunkBlobs,
+	chunks_store::{ ChunksStore, STATE },
+	legacy_store::LegacyChunksStore,
+};
 
 #[pre_upgrade]
 fn pre_upgrade() {
-	STATE.with(|state| storage::stable_save((state,)).unwrap());
+	// The blobs are already in stable memory, only the rest of the store is saved
+	STATE.with(|state| stable_memory::save_state(&*state.borrow()));
 }
 
 #[post_upgrade]
 fn post_upgrade() {
-	let (old_store,): (ChunksStore,) = storage::stable_restore().unwrap();
+	let old_store = if stable_memory::is_memory_managed() {
+		stable_memory::restore_state::<ChunksStore>().unwrap()
+	} else {
+		// Canisters deployed before the blobs were kept in stable memory hold the whole legacy store
+		let (legacy_store,): (LegacyChunksStore,) = storage::stable_restore().unwrap();
+		ChunksStore::from(legacy_store)
+	};
+
 	STATE.with(|state| {
 		*state.borrow_mut() = old_store;
 	});
+
+	ChunksStore::certify_http_assets();
+	ChunksStore::start_upload_session_purge_timer();
 }
 
 // ========== Admin calls
@@ -35,7 +55,7 @@ fn get_state() -> Result<ChunkStoreState, ApiError> {
 					ChunkStoreState {
 						canister_owner: state.canister_owner,
 						chunk_id: state.chunk_id,
-						chunks: state.chunks.keys().cloned().collect(),
+						chunks: ChunkBlobs::keys(),
 					}
 				})
 			),
@@ -61,8 +81,74 @@ fn delete_chunks_intercanister_call(caller_principal: Principal, chunk_ids: Vec<
 	}
 }
 
+#[update]
+#[candid_method(update)]
+fn copy_chunks_intercanister_call(chunks: Vec<Chunk>) -> Result<Vec<Chunk>, ApiError> {
+	match validate_admin(&caller()) {
+		Ok(_) => ChunksStore::copy_chunks_intercanister_call(chunks),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_chunk_intercanister_call(chunk_id: u32) -> Result<Vec<u8>, ApiError> {
+	match validate_admin(&caller()) {
+		Ok(_) => ChunksStore::get_chunk_intercanister_call(chunk_id),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_chunk_hashes_intercanister_call(chunk_ids: Vec<u32>) -> Result<Vec<Vec<u8>>, ApiError> {
+	match validate_admin(&caller()) {
+		Ok(_) => ChunksStore::get_chunk_hashes_intercanister_call(chunk_ids),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn add_chunk_intercanister_call(chunk: PostChunk) -> Result<Chunk, ApiError> {
+	match validate_admin(&caller()) {
+		Ok(_) => ChunksStore::add_chunk_intercanister_call(chunk),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn set_http_asset(http_asset: HttpAsset) -> Result<HttpAsset, ApiError> {
+	match validate_admin(&caller()) {
+		Ok(_) => ChunksStore::set_http_asset(http_asset),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn remove_http_asset(asset_id: u32) -> Result<u32, ApiError> {
+	match validate_admin(&caller()) {
+		Ok(_) => ChunksStore::remove_http_asset(asset_id),
+		Err(err) => Err(err),
+	}
+}
+
 // ========== Non-admin calls
 
+#[query]
+#[candid_method(query)]
+fn http_request(request: HttpRequest) -> HttpResponse {
+	ChunksStore::http_request(request)
+}
+
+#[query]
+#[candid_method(query)]
+fn http_request_streaming_callback(token: StreamingCallbackToken) -> StreamingCallbackHttpResponse {
+	ChunksStore::http_request_streaming_callback(token)
+}
+
 #[query]
 #[candid_method(query)]
 fn get_chunks_by_chunk_id(chunk_id: u32) -> Result<Vec<u8>, ApiError> {
@@ -81,6 +167,51 @@ fn add_chunk(chunk: PostChunk) -> Result<Chunk, ApiError> {
 	}
 }
 
+#[update]
+#[candid_method(update)]
+fn begin_upload(total_size: u64, chunk_count: u32) -> Result<UploadSession, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ChunksStore::begin_upload(caller_principal, total_size, chunk_count),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn put_chunk(session_id: u32, index: u32, blob: Vec<u8>) -> Result<Chunk, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ChunksStore::put_chunk(caller_principal, session_id, index, blob),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+#[candid_method(query)]
+fn get_upload_status(session_id: u32) -> Result<UploadStatus, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ChunksStore::get_upload_status(caller_principal, session_id),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn commit_upload(session_id: u32, hash: Vec<u8>) -> Result<Vec<Chunk>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ChunksStore::commit_upload(caller_principal, session_id, hash),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+#[candid_method(update)]
+fn abort_upload(session_id: u32) -> Result<u32, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ChunksStore::abort_upload(caller_principal, session_id),
+		Err(err) => Err(err),
+	}
+}
+
 #[update]
 #[candid_method(update)]
 fn delete_chunks(chunk_ids: Vec<u32>) -> Result<Vec<u32>, ApiError> {
@@ -99,9 +230,14 @@ fn init(canister_owner: Option<Principal>) {
 
 			state.canister_owner = owner;
 			state.chunk_id = 0;
-			state.chunks = HashMap::new();
+			state.http_assets = HashMap::new();
+			state.http_asset_hashes = HashMap::new();
+			state.upload_session_id = 0;
+			state.upload_sessions = HashMap::new();
 		}
 	});
+
+	ChunksStore::start_upload_session_purge_timer();
 }
 
 #[test]
@@ -1,16 +1,62 @@
+use crate::{
+	certified_responses::{
+		response_hash,
+		CertifiedResponses,
+		CERTIFICATE_EXPRESSION,
+		CERTIFICATE_EXPRESSION_HEADER,
+		EXACT_PATH_LABEL,
+	},
+	chunk_blobs::ChunkBlobs,
+};
 use candid::{ CandidType, Deserialize, Principal };
-use ic_cdk::id;
-use lib::types::{ api_error::ApiError, chunk::{ Chunk, PostChunk } };
-use std::{ cell::RefCell, collections::HashMap };
+use ic_cdk::{ api::{ data_certificate, set_certified_data, time }, id, timer::{ set_timer, set_timer_interval } };
+use ic_certified_map::{ labeled, labeled_hash, AsHashTree, Hash, RbTree };
+use lib::{
+	types::{
+		api_error::ApiError,
+		chunk::{ Chunk, PostChunk, UploadSession, UploadStatus, UploadedChunk },
+		http::{
+			HeaderField,
+			HttpAsset,
+			HttpRequest,
+			HttpResponse,
+			StreamingCallback,
+			StreamingCallbackHttpResponse,
+			StreamingCallbackToken,
+			StreamingStrategy,
+		},
+	},
+	utils::{ hash_chunk, hash_file },
+};
+use serde::Serialize;
+use sha2::{ Digest, Sha256 };
+use std::{ cell::RefCell, collections::HashMap, time::Duration };
+
+// Label of the certified tree that holds the responses of the assets served over HTTP
+const LABEL_HTTP_EXPR: &str = "http_expr";
+// Time an upload session is kept without receiving chunks in nanoseconds
+const UPLOAD_SESSION_EXPIRY: u64 = 24 * 60 * 60 * 1_000_000_000;
+// Time between two purges of expired upload sessions
+const UPLOAD_SESSION_PURGE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// Maximum number of bytes hashed per message when assets start being served over HTTP. A step ends after the chunk
+// that exceeds it
+const HASH_STEP_SIZE: usize = 64 * 1024 * 1024;
 
 #[derive(CandidType, Clone, Deserialize)]
 pub struct ChunksStore {
 	// Caller's principal
 	pub canister_owner: Principal,
-	// Increment of chunk IDs
+	// Increment of chunk IDs. The blobs of the chunks are kept in stable memory, see `ChunkBlobs`
 	pub chunk_id: u32,
-	// Blobs (u8) of the chunks. u32 = chunk_id, Principal = caller
-	pub chunks: HashMap<(u32, Principal), Vec<u8>>,
+	// Public assets that are served over HTTP. u32 = asset_id
+	pub http_assets: HashMap<u32, HttpAsset>,
+	// SHA-256 hashes of the content of the assets that are served over HTTP. Assets are only served once their
+	// content is hashed. u32 = asset_id
+	pub http_asset_hashes: HashMap<u32, Vec<u8>>,
+	// Increment of upload session IDs
+	pub upload_session_id: u32,
+	// Uploads that are not committed yet. Their chunks are removed when they expire. u32 = upload_session_id
+	pub upload_sessions: HashMap<u32, UploadSession>,
 }
 
 impl Default for ChunksStore {
@@ -18,13 +64,29 @@ impl Default for ChunksStore {
 		Self {
 			canister_owner: Principal::anonymous(),
 			chunk_id: Default::default(),
-			chunks: Default::default(),
+			http_assets: Default::default(),
+			http_asset_hashes: Default::default(),
+			upload_session_id: Default::default(),
+			upload_sessions: Default::default(),
 		}
 	}
 }
 
+// Hash of the content of an asset that is computed across messages
+#[derive(Default)]
+struct HashJob {
+	hasher: Sha256,
+	// Index of the next chunk to hash
+	position: usize,
+}
+
 thread_local! {
 	pub static STATE: RefCell<ChunksStore> = RefCell::new(ChunksStore::default());
+	// Assets whose content is being hashed before they are served over HTTP. u32 = asset_id
+	static HASH_JOBS: RefCell<HashMap<u32, HashJob>> = RefCell::new(HashMap::new());
+	// Certified responses of the assets served over HTTP. String = asset ID, the URL path of the asset without the
+	// leading slash
+	static CERTIFIED_RESPONSES: RefCell<RbTree<String, CertifiedResponses>> = const { RefCell::new(RbTree::new()) };
 }
 
 impl ChunksStore {
@@ -35,11 +97,269 @@ impl ChunksStore {
 	/// # Returns
 	/// - `HashMap<(u32, Principal), Vec<u8>>` - Chunks
 	pub fn get_all_chunks() -> HashMap<(u32, Principal), Vec<u8>> {
-		STATE.with(|state| state.borrow().chunks.clone())
+		ChunkBlobs::keys()
+			.into_iter()
+			.filter_map(|(chunk_id, principal)| ChunkBlobs::get(chunk_id, principal).map(|blob| ((chunk_id, principal), blob)))
+			.collect()
+	}
+
+	/// Set an asset that can be served over HTTP. This should only be called by the `assets` canister when
+	/// an asset is public. Only chunks of the canister owner can be served. The content is hashed in steps across
+	/// messages and the asset is served once the hash is added to the certified data, so that the responses can be
+	/// verified by the boundary nodes.
+	///
+	/// # Arguments
+	/// - `http_asset` - Asset to serve
+	///
+	/// # Returns
+	/// - `HttpAsset` - Asset that is served
+	pub fn set_http_asset(mut http_asset: HttpAsset) -> Result<HttpAsset, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			if http_asset.owner != state.canister_owner {
+				return Err(ApiError::Unauthorized("UNAUTHORIZED".to_string()));
+			}
+
+			// Serve the chunks in the order they were uploaded
+			http_asset.chunks.sort_by_key(|chunk| chunk.index);
+
+			if !http_asset.chunks.iter().all(|chunk| ChunkBlobs::len(chunk.id, state.canister_owner).is_some()) {
+				return Err(ApiError::NotFound("CHUNKS_NOT_FOUND".to_string()));
+			}
+
+			// The previous content stops being served until the new content is hashed
+			state.http_asset_hashes.remove(&http_asset.asset_id);
+			state.http_assets.insert(http_asset.asset_id, http_asset.clone());
+
+			Self::certify_http_asset(&state, http_asset.asset_id);
+			Self::start_hash_job(http_asset.asset_id);
+
+			Ok(http_asset)
+		})
+	}
+
+	/// Remove an asset from being served over HTTP. This should only be called by the `assets` canister when
+	/// an asset is no longer public or deleted.
+	///
+	/// # Arguments
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `u32` - Asset ID that was removed
+	pub fn remove_http_asset(asset_id: u32) -> Result<u32, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			state.http_asset_hashes.remove(&asset_id);
+			HASH_JOBS.with(|hash_jobs| hash_jobs.borrow_mut().remove(&asset_id));
+
+			let removed_asset = state.http_assets.remove(&asset_id);
+			Self::certify_http_asset(&state, asset_id);
+
+			removed_asset
+				.map(|_| asset_id)
+				.ok_or(ApiError::NotFound("ASSET_NOT_FOUND".to_string()))
+		})
+	}
+
+	/// Rebuild the certified data from the assets served over HTTP and restart hashing the assets
+	/// that were not hashed yet. The certified tree and the hashes in progress are not kept in stable memory, so
+	/// this has to be called after an upgrade.
+	///
+	/// # Returns
+	/// - `()` - No return value
+	pub fn certify_http_assets() {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			CERTIFIED_RESPONSES.with(|certified_responses| {
+				let mut certified_responses = certified_responses.borrow_mut();
+
+				for asset_id in state.http_asset_hashes.keys() {
+					if let Some(asset_responses) = Self::certified_asset_responses(&state, *asset_id) {
+						certified_responses.insert(asset_id.to_string(), asset_responses);
+					}
+				}
+
+				set_certified_data(&labeled_hash(LABEL_HTTP_EXPR.as_bytes(), &certified_responses.root_hash()));
+			});
+
+			for asset_id in state.http_assets.keys() {
+				if !state.http_asset_hashes.contains_key(asset_id) {
+					Self::start_hash_job(*asset_id);
+				}
+			}
+		})
+	}
+
+	/// Start the timer that purges expired upload sessions. Timers don't survive upgrades, so this is called on
+	/// install and after every upgrade.
+	///
+	/// # Returns
+	/// - `()` - No return value
+	pub fn start_upload_session_purge_timer() {
+		set_timer_interval(UPLOAD_SESSION_PURGE_INTERVAL, Self::purge_upload_sessions);
+	}
+
+	/// Remove upload sessions that expired and the chunks they received.
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn purge_upload_sessions() {
+		let now = time();
+
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+			let canister_owner = state.canister_owner;
+
+			let expired_session_ids: Vec<u32> = state.upload_sessions
+				.values()
+				.filter(|upload_session| upload_session.expires_at <= now)
+				.map(|upload_session| upload_session.id)
+				.collect();
+
+			for session_id in expired_session_ids {
+				if let Some(upload_session) = state.upload_sessions.remove(&session_id) {
+					Self::remove_session_chunks(&upload_session, canister_owner);
+				}
+			}
+		})
 	}
 
 	// ========== Non-admin calls
 
+	/// Serve a public asset over HTTP. The URL path is the asset ID, e.g. `/1`.
+	/// The first chunk is returned directly, the remaining chunks are streamed. Requests with a single byte
+	/// range are answered with the whole chunk the range starts in, as only whole chunks are certified. HEAD
+	/// requests are answered without a body.
+	///
+	/// # Arguments
+	/// - `request` - HTTP request
+	///
+	/// # Returns
+	/// - `HttpResponse` - HTTP response
+	pub fn http_request(request: HttpRequest) -> HttpResponse {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			if request.method != "GET" && request.method != "HEAD" {
+				return Self::http_error(405, "Method not allowed");
+			}
+
+			// Get the asset ID from the URL path. Assets are only served once their content is hashed and certified
+			let path = request.url.split('?').next().unwrap_or_default();
+			let opt_http_asset = path
+				.strip_prefix('/')
+				.and_then(|asset_id| asset_id.parse::<u32>().ok())
+				.and_then(|asset_id| {
+					let hash = Hash::try_from(state.http_asset_hashes.get(&asset_id)?.as_slice()).ok()?;
+					state.http_assets.get(&asset_id).map(|http_asset| (http_asset, hash))
+				});
+
+			let (http_asset, hash) = match opt_http_asset {
+				Some((http_asset, hash)) => (http_asset, hash),
+				None => {
+					return Self::http_error(404, "Not found");
+				}
+			};
+
+			// All chunks of the asset have to be stored in this canister. Only the lengths are read here, blobs are
+			// read from stable memory when they are returned
+			let (chunk_ranges, length) = match Self::chunk_ranges(&state, http_asset) {
+				Some((chunk_ranges, length)) if !chunk_ranges.is_empty() => (chunk_ranges, length),
+				_ => {
+					return Self::http_error(404, "Not found");
+				}
+			};
+
+			if request.method == "HEAD" {
+				let mut headers = Self::http_asset_headers(http_asset, None);
+				headers.extend(Self::certificate_header(http_asset.asset_id, &response_hash(200, &headers, &Self::empty_body_hash())));
+
+				return HttpResponse {
+					status_code: 200,
+					headers,
+					body: vec![],
+					streaming_strategy: None,
+				};
+			}
+
+			if let Some(range) = Self::find_range(&request.headers) {
+				let start = match Self::parse_range(range, length) {
+					Some((start, _)) => start,
+					None => {
+						let mut response = Self::http_error(416, "Range not satisfiable");
+						response.headers.push(("Content-Range".to_string(), format!("bytes */{}", length)));
+
+						return response;
+					}
+				};
+
+				// Return the chunk the range starts in
+				let opt_chunk = http_asset.chunks
+					.iter()
+					.zip(&chunk_ranges)
+					.find(|(_, (_, chunk_end))| start < *chunk_end);
+
+				if let Some((chunk, (chunk_start, chunk_end))) = opt_chunk {
+					let mut headers = Self::http_asset_headers(http_asset, Some(Self::content_range(*chunk_start, *chunk_end, length)));
+
+					if let Some(chunk_hash) = chunk.hash.as_deref().and_then(|chunk_hash| Hash::try_from(chunk_hash).ok()) {
+						headers.extend(Self::certificate_header(http_asset.asset_id, &response_hash(206, &headers, &chunk_hash)));
+					}
+
+					return HttpResponse {
+						status_code: 206,
+						headers,
+						body: ChunkBlobs::get(chunk.id, state.canister_owner).unwrap_or_default(),
+						streaming_strategy: None,
+					};
+				}
+			}
+
+			let streaming_strategy = Self::next_streaming_token(http_asset, 0).map(|token| StreamingStrategy::Callback {
+				callback: StreamingCallback::new(id(), "http_request_streaming_callback"),
+				token,
+			});
+
+			let mut headers = Self::http_asset_headers(http_asset, None);
+			headers.extend(Self::certificate_header(http_asset.asset_id, &response_hash(200, &headers, &hash)));
+
+			HttpResponse {
+				status_code: 200,
+				headers,
+				body: ChunkBlobs::get(http_asset.chunks[0].id, state.canister_owner).unwrap_or_default(),
+				streaming_strategy,
+			}
+		})
+	}
+
+	/// Get the next chunk of an asset that is streamed over HTTP.
+	///
+	/// # Arguments
+	/// - `token` - Token of the chunk to return
+	///
+	/// # Returns
+	/// - `StreamingCallbackHttpResponse` - Chunk and the token of the next chunk
+	pub fn http_request_streaming_callback(token: StreamingCallbackToken) -> StreamingCallbackHttpResponse {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			let opt_http_asset = state.http_assets.get(&token.asset_id);
+
+			let body = opt_http_asset
+				.and_then(|http_asset| http_asset.chunks.get(token.index as usize))
+				.and_then(|chunk| ChunkBlobs::get(chunk.id, state.canister_owner))
+				.unwrap_or_default();
+
+			StreamingCallbackHttpResponse {
+				body,
+				token: opt_http_asset.and_then(|http_asset| Self::next_streaming_token(http_asset, token.index)),
+			}
+		})
+	}
+
 	/// Get chunks by chunk ID.
 	///
 	/// # Arguments
@@ -58,10 +378,10 @@ impl ChunksStore {
 			}
 
 			// Get chunks linked to the chunk ID and principal (caller)
-			let opt_chunks = state.chunks.get(&(chunk_id, caller_principal));
+			let opt_chunks = ChunkBlobs::get(chunk_id, caller_principal);
 
 			if let Some(chunks) = opt_chunks {
-				Ok(chunks.clone())
+				Ok(chunks)
 			} else {
 				Err(ApiError::NotFound("CHUNKS_NOT_FOUND".to_string()))
 			}
@@ -90,16 +410,226 @@ impl ChunksStore {
 			let chunk_id = state.chunk_id;
 
 			// Add chunk linked to the chunk and principal (caller)
-			state.chunks.insert((chunk_id, caller_principal), post_chunk.blob);
+			ChunkBlobs::insert(chunk_id, caller_principal, &post_chunk.blob);
 
 			Ok(Chunk {
 				id: chunk_id,
 				index: post_chunk.index,
 				canister: id(),
+				owner: Some(caller_principal),
+				hash: Some(hash_chunk(&post_chunk.blob)),
 			})
 		})
 	}
 
+	/// Start an upload of a file that is split into chunks. The chunks are added with `put_chunk` and only kept once
+	/// the upload is committed. Uploads that don't receive chunks before they expire are removed with their chunks.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `total_size` - Size of the file in bytes
+	/// - `chunk_count` - Number of chunks the file is split into
+	///
+	/// # Returns
+	/// - `UploadSession` - Started upload
+	pub fn begin_upload(caller_principal: Principal, total_size: u64, chunk_count: u32) -> Result<UploadSession, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			if caller_principal != state.canister_owner {
+				// If the caller is not the canister owner, return an error
+				return Err(ApiError::NotFound("UNAUTHORIZED".to_string()));
+			}
+
+			if chunk_count == 0 {
+				return Err(ApiError::BadRequest("INVALID_CHUNK_COUNT".to_string()));
+			}
+
+			// Increment upload session ID
+			state.upload_session_id += 1;
+			let session_id = state.upload_session_id;
+
+			let upload_session = UploadSession {
+				id: session_id,
+				total_size,
+				chunk_count,
+				chunks: Default::default(),
+				received_size: 0,
+				expires_at: time() + UPLOAD_SESSION_EXPIRY,
+			};
+
+			state.upload_sessions.insert(session_id, upload_session.clone());
+
+			Ok(upload_session)
+		})
+	}
+
+	/// Add a chunk to an upload. A chunk that is put again for the same index replaces the previous one, so failed
+	/// chunks can be retried. Every chunk extends the expiry of the upload.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `session_id` - Upload session ID
+	/// - `index` - Index of the chunk in the file
+	/// - `blob` - Blob of the chunk
+	///
+	/// # Returns
+	/// - `Chunk` - Chunk added
+	pub fn put_chunk(caller_principal: Principal, session_id: u32, index: u32, blob: Vec<u8>) -> Result<Chunk, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			let mut upload_session = Self::find_upload_session(&state, &caller_principal, session_id)?;
+
+			if index >= upload_session.chunk_count {
+				return Err(ApiError::BadRequest("INVALID_CHUNK_INDEX".to_string()));
+			}
+
+			// A chunk that is put again replaces the previous chunk of the index
+			let previous_chunk = upload_session.chunks.get(&index).cloned();
+			let previous_size = previous_chunk
+				.as_ref()
+				.and_then(|chunk| ChunkBlobs::len(chunk.id, caller_principal))
+				.unwrap_or_default() as u64;
+
+			let received_size = upload_session.received_size - previous_size + blob.len() as u64;
+
+			if received_size > upload_session.total_size {
+				return Err(ApiError::BadRequest("UPLOAD_SIZE_EXCEEDED".to_string()));
+			}
+
+			let chunk_id = match previous_chunk {
+				Some(chunk) => chunk.id,
+				None => {
+					// Increment asset chunk ID
+					state.chunk_id += 1;
+					state.chunk_id
+				}
+			};
+
+			let chunk = Chunk {
+				id: chunk_id,
+				index,
+				canister: id(),
+				owner: Some(caller_principal),
+				hash: Some(hash_chunk(&blob)),
+			};
+
+			ChunkBlobs::insert(chunk.id, caller_principal, &blob);
+
+			// Mutate values
+			upload_session.chunks.insert(index, chunk.clone());
+			upload_session.received_size = received_size;
+			upload_session.expires_at = time() + UPLOAD_SESSION_EXPIRY;
+
+			state.upload_sessions.insert(session_id, upload_session);
+
+			Ok(chunk)
+		})
+	}
+
+	/// Get the chunks an upload already received, so an interrupted upload can be continued with the missing
+	/// indexes. Only chunks whose blob is stored are returned, with the size read from the stored blob.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `session_id` - Upload session ID
+	///
+	/// # Returns
+	/// - `UploadStatus` - Received chunks, received size and expiry of the upload
+	pub fn get_upload_status(caller_principal: Principal, session_id: u32) -> Result<UploadStatus, ApiError> {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			let upload_session = Self::find_upload_session(&state, &caller_principal, session_id)?;
+
+			let chunks: Vec<UploadedChunk> = upload_session.chunks
+				.values()
+				.filter_map(|chunk| {
+					ChunkBlobs::len(chunk.id, caller_principal).map(|size| UploadedChunk {
+						index: chunk.index,
+						chunk_id: chunk.id,
+						size: size as u64,
+						hash: chunk.hash.clone(),
+					})
+				})
+				.collect();
+
+			Ok(UploadStatus {
+				session_id,
+				total_size: upload_session.total_size,
+				chunk_count: upload_session.chunk_count,
+				received_size: chunks.iter().map(|chunk| chunk.size).sum(),
+				chunks,
+				expires_at: upload_session.expires_at,
+			})
+		})
+	}
+
+	/// Commit an upload. All chunks have to be received, add up to the size of the file and match the hash of the
+	/// file. The chunks are kept and can be used for an asset, the upload session is removed.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `session_id` - Upload session ID
+	/// - `hash` - Expected hash of the file, the SHA-256 hash of the hashes of the chunks in the order of their index
+	///
+	/// # Returns
+	/// - `Vec<Chunk>` - Chunks of the file in the order of their index
+	pub fn commit_upload(caller_principal: Principal, session_id: u32, hash: Vec<u8>) -> Result<Vec<Chunk>, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			let upload_session = Self::find_upload_session(&state, &caller_principal, session_id)?;
+
+			if upload_session.chunks.len() != upload_session.chunk_count as usize {
+				return Err(ApiError::BadRequest("UPLOAD_INCOMPLETE".to_string()));
+			}
+
+			if upload_session.received_size != upload_session.total_size {
+				return Err(ApiError::BadRequest("UPLOAD_SIZE_MISMATCH".to_string()));
+			}
+
+			let chunks: Vec<Chunk> = upload_session.chunks.into_values().collect();
+
+			if hash_file(&chunks) != Some(hash) {
+				return Err(ApiError::BadRequest("UPLOAD_HASH_MISMATCH".to_string()));
+			}
+
+			state.upload_sessions.remove(&session_id);
+
+			Ok(chunks)
+		})
+	}
+
+	/// Abort an upload and remove the chunks it received.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	/// - `session_id` - Upload session ID
+	///
+	/// # Returns
+	/// - `u32` - Upload session ID that was aborted
+	pub fn abort_upload(caller_principal: Principal, session_id: u32) -> Result<u32, ApiError> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+
+			if caller_principal != state.canister_owner {
+				// If the caller is not the canister owner, return an error
+				return Err(ApiError::NotFound("UNAUTHORIZED".to_string()));
+			}
+
+			// Expired uploads can be aborted as well, their chunks are removed right away
+			let upload_session = state.upload_sessions
+				.remove(&session_id)
+				.ok_or(ApiError::NotFound("UPLOAD_SESSION_NOT_FOUND".to_string()))?;
+
+			Self::remove_session_chunks(&upload_session, caller_principal);
+
+			Ok(session_id)
+		})
+	}
+
 	/// Delete chunks.
 	///
 	/// # Arguments
@@ -110,7 +640,7 @@ impl ChunksStore {
 	/// - `Vec<u32>` - Chunk IDs that were deleted
 	pub fn delete_chunks(caller_principal: Principal, delete_chunk_ids: Vec<u32>) -> Result<Vec<u32>, ApiError> {
 		STATE.with(|state| {
-			let mut state = state.borrow_mut();
+			let state = state.borrow();
 			let mut removed_chunk_ids = Vec::new();
 
 			if caller_principal != state.canister_owner {
@@ -119,7 +649,7 @@ impl ChunksStore {
 			}
 
 			for id in delete_chunk_ids {
-				if state.chunks.remove(&(id, caller_principal)).is_some() {
+				if ChunkBlobs::remove(id, caller_principal) {
 					removed_chunk_ids.push(id);
 				}
 			}
@@ -129,7 +659,7 @@ impl ChunksStore {
 	}
 
 	/// Delete chunks. This should only be called by the `assets` canister to delete old chunks when
-	/// uploading the exact same asset.
+	/// uploading the exact same asset or when assets are deleted.
 	///
 	/// # Arguments
 	/// - `caller_principal` - Principal of the caller
@@ -141,18 +671,514 @@ impl ChunksStore {
 		caller_principal: Principal,
 		delete_chunk_ids: Vec<u32>
 	) -> Result<Vec<u32>, ApiError> {
+		let mut removed_chunk_ids = Vec::new();
+
+		// Delete chunks linked to the chunk IDs and principal (caller)
+		for id in delete_chunk_ids {
+			if ChunkBlobs::remove(id, caller_principal) {
+				removed_chunk_ids.push(id);
+			}
+		}
+
+		Ok(removed_chunk_ids)
+	}
+
+	/// Copy chunks within this canister. This should only be called by the `assets` canister when copying assets.
+	/// The copies keep the index of the original chunks.
+	///
+	/// # Arguments
+	/// - `chunks` - Chunks to copy
+	///
+	/// # Returns
+	/// - `Vec<Chunk>` - Copied chunks
+	pub fn copy_chunks_intercanister_call(chunks: Vec<Chunk>) -> Result<Vec<Chunk>, ApiError> {
 		STATE.with(|state| {
 			let mut state = state.borrow_mut();
-			let mut removed_chunk_ids = Vec::new();
+			let canister_owner = state.canister_owner;
 
-			// Delete chunks linked to the chunk IDs and principal (caller)
-			for id in delete_chunk_ids {
-				if state.chunks.remove(&(id, caller_principal)).is_some() {
-					removed_chunk_ids.push(id);
+			// Make sure all chunks exist before anything is copied
+			let blobs: Vec<Vec<u8>> = chunks
+				.iter()
+				.map(|chunk| ChunkBlobs::get(chunk.id, canister_owner))
+				.collect::<Option<_>>()
+				.ok_or(ApiError::NotFound("CHUNKS_NOT_FOUND".to_string()))?;
+
+			let mut copied_chunks: Vec<Chunk> = vec![];
+
+			for (chunk, blob) in chunks.iter().zip(blobs) {
+				// Increment asset chunk ID
+				state.chunk_id += 1;
+				let chunk_id = state.chunk_id;
+
+				ChunkBlobs::insert(chunk_id, canister_owner, &blob);
+
+				copied_chunks.push(Chunk {
+					id: chunk_id,
+					index: chunk.index,
+					canister: id(),
+					owner: Some(canister_owner),
+					hash: Some(hash_chunk(&blob)),
+				});
+			}
+
+			Ok(copied_chunks)
+		})
+	}
+
+	/// Get a chunk. This should only be called by the `assets` canister to copy a chunk to another canister.
+	///
+	/// # Arguments
+	/// - `chunk_id` - Chunk ID
+	///
+	/// # Returns
+	/// - `Vec<u8>` - Blob of the chunk
+	pub fn get_chunk_intercanister_call(chunk_id: u32) -> Result<Vec<u8>, ApiError> {
+		STATE.with(|state| {
+			let state = state.borrow();
+
+			ChunkBlobs::get(chunk_id, state.canister_owner).ok_or(ApiError::NotFound("CHUNKS_NOT_FOUND".to_string()))
+		})
+	}
+
+	/// Get the hashes of chunks. This should only be called by the `assets` canister to check the hash of a file
+	/// against the stored blobs instead of the hashes sent by the client.
+	///
+	/// # Arguments
+	/// - `chunk_ids` - Chunk IDs
+	///
+	/// # Returns
+	/// - `Vec<Vec<u8>>` - SHA-256 hashes of the blobs in the order of the chunk IDs
+	pub fn get_chunk_hashes_intercanister_call(chunk_ids: Vec<u32>) -> Result<Vec<Vec<u8>>, ApiError> {
+		STATE.with(|state| {
+			let canister_owner = state.borrow().canister_owner;
+
+			chunk_ids
+				.into_iter()
+				.map(|chunk_id| ChunkBlobs::hash(chunk_id, canister_owner))
+				.collect::<Option<_>>()
+				.ok_or(ApiError::NotFound("CHUNKS_NOT_FOUND".to_string()))
+		})
+	}
+
+	/// Add a chunk for the canister owner. This should only be called by the `assets` canister to copy a chunk
+	/// from another canister.
+	///
+	/// # Arguments
+	/// - `post_chunk` - Chunk to add
+	///
+	/// # Returns
+	/// - `Chunk` - Chunk added
+	pub fn add_chunk_intercanister_call(post_chunk: PostChunk) -> Result<Chunk, ApiError> {
+		let canister_owner = STATE.with(|state| state.borrow().canister_owner);
+
+		Self::add_chunk(canister_owner, post_chunk)
+	}
+
+	/// Find an upload session of the caller that has not expired.
+	///
+	/// # Arguments
+	/// - `state` - Chunks store
+	/// - `caller_principal` - Principal of the caller
+	/// - `session_id` - Upload session ID
+	///
+	/// # Returns
+	/// - `UploadSession` - Upload session
+	fn find_upload_session(state: &ChunksStore, caller_principal: &Principal, session_id: u32) -> Result<UploadSession, ApiError> {
+		if *caller_principal != state.canister_owner {
+			// If the caller is not the canister owner, return an error
+			return Err(ApiError::NotFound("UNAUTHORIZED".to_string()));
+		}
+
+		let upload_session = state.upload_sessions
+			.get(&session_id)
+			.cloned()
+			.ok_or(ApiError::NotFound("UPLOAD_SESSION_NOT_FOUND".to_string()))?;
+
+		if upload_session.expires_at <= time() {
+			return Err(ApiError::BadRequest("UPLOAD_SESSION_EXPIRED".to_string()));
+		}
+
+		Ok(upload_session)
+	}
+
+	/// Remove the chunks an upload session received.
+	///
+	/// # Arguments
+	/// - `upload_session` - Upload session
+	/// - `canister_owner` - Principal the chunks belong to
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn remove_session_chunks(upload_session: &UploadSession, canister_owner: Principal) {
+		for chunk in upload_session.chunks.values() {
+			ChunkBlobs::remove(chunk.id, canister_owner);
+		}
+	}
+
+	/// Start hashing the content of an asset before it is served over HTTP. A hash in progress is restarted.
+	///
+	/// # Arguments
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn start_hash_job(asset_id: u32) {
+		let is_hashing = HASH_JOBS.with(|hash_jobs| {
+			let mut hash_jobs = hash_jobs.borrow_mut();
+			let is_hashing = !hash_jobs.is_empty();
+
+			hash_jobs.insert(asset_id, HashJob::default());
+
+			is_hashing
+		});
+
+		// A step is already scheduled while other assets are hashed
+		if !is_hashing {
+			set_timer(Duration::ZERO, Self::hash_http_assets);
+		}
+	}
+
+	/// Hash the next chunks of the assets that are about to be served over HTTP, up to `HASH_STEP_SIZE` bytes.
+	/// Assets are certified once all of their chunks are hashed. The next step is scheduled as long as assets are
+	/// left.
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn hash_http_assets() {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+			let canister_owner = state.canister_owner;
+
+			HASH_JOBS.with(|hash_jobs| {
+				let mut hash_jobs = hash_jobs.borrow_mut();
+				let asset_ids: Vec<u32> = hash_jobs.keys().copied().collect();
+				let mut hashed_size: usize = 0;
+
+				for asset_id in asset_ids {
+					if hashed_size >= HASH_STEP_SIZE {
+						break;
+					}
+
+					let (Some(http_asset), Some(hash_job)) = (state.http_assets.get_mut(&asset_id), hash_jobs.get_mut(&asset_id)) else {
+						hash_jobs.remove(&asset_id);
+						continue;
+					};
+
+					let mut is_chunk_missing = false;
+
+					while hashed_size < HASH_STEP_SIZE && hash_job.position < http_asset.chunks.len() {
+						let chunk = &mut http_asset.chunks[hash_job.position];
+
+						let Some(blob) = ChunkBlobs::get(chunk.id, canister_owner) else {
+							is_chunk_missing = true;
+							break;
+						};
+
+						// Ranges are answered with whole chunks, which are certified with the hashes of their blobs
+						chunk.hash = ChunkBlobs::hash(chunk.id, canister_owner);
+						hash_job.hasher.update(&blob);
+						hash_job.position += 1;
+						hashed_size += blob.len();
+					}
+
+					// Assets with deleted chunks are not served
+					if is_chunk_missing {
+						hash_jobs.remove(&asset_id);
+					} else if hash_job.position == http_asset.chunks.len() {
+						let hash: Hash = hash_jobs.remove(&asset_id).unwrap_or_default().hasher.finalize().into();
+
+						state.http_asset_hashes.insert(asset_id, hash.to_vec());
+						Self::certify_http_asset(&state, asset_id);
+					}
 				}
+
+				if !hash_jobs.is_empty() {
+					set_timer(Duration::ZERO, Self::hash_http_assets);
+				}
+			})
+		})
+	}
+
+	/// Add or remove the responses of an asset in the certified tree and update the certified data of the
+	/// canister. The responses are removed if the asset isn't served.
+	///
+	/// # Arguments
+	/// - `state` - Chunks store
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn certify_http_asset(state: &ChunksStore, asset_id: u32) {
+		CERTIFIED_RESPONSES.with(|certified_responses| {
+			let mut certified_responses = certified_responses.borrow_mut();
+			let path = asset_id.to_string();
+
+			match Self::certified_asset_responses(state, asset_id) {
+				Some(asset_responses) => certified_responses.insert(path, asset_responses),
+				None => certified_responses.delete(path.as_bytes()),
 			}
 
-			Ok(removed_chunk_ids)
+			set_certified_data(&labeled_hash(LABEL_HTTP_EXPR.as_bytes(), &certified_responses.root_hash()));
 		})
 	}
+
+	/// Certify the responses of an asset that `http_request` returns: the whole asset, the asset without a body
+	/// for HEAD requests and every chunk for range requests.
+	///
+	/// # Arguments
+	/// - `state` - Chunks store
+	/// - `asset_id` - Asset ID
+	///
+	/// # Returns
+	/// - `Option<CertifiedResponses>` - Responses, if the asset is served and its content is hashed
+	fn certified_asset_responses(state: &ChunksStore, asset_id: u32) -> Option<CertifiedResponses> {
+		let http_asset = state.http_assets.get(&asset_id)?;
+		let hash = Hash::try_from(state.http_asset_hashes.get(&asset_id)?.as_slice()).ok()?;
+		let (chunk_ranges, length) = Self::chunk_ranges(state, http_asset)?;
+
+		let mut asset_responses = CertifiedResponses::default();
+		let headers = Self::http_asset_headers(http_asset, None);
+
+		asset_responses.insert(response_hash(200, &headers, &hash));
+		asset_responses.insert(response_hash(200, &headers, &Self::empty_body_hash()));
+
+		for (chunk, (chunk_start, chunk_end)) in http_asset.chunks.iter().zip(chunk_ranges) {
+			let chunk_hash = chunk.hash.as_deref().and_then(|chunk_hash| Hash::try_from(chunk_hash).ok());
+
+			if let Some(chunk_hash) = chunk_hash.filter(|_| chunk_start < chunk_end) {
+				let headers = Self::http_asset_headers(http_asset, Some(Self::content_range(chunk_start, chunk_end, length)));
+				asset_responses.insert(response_hash(206, &headers, &chunk_hash));
+			}
+		}
+
+		Some(asset_responses)
+	}
+
+	/// Get the byte ranges of the chunks of an asset, if all chunks are stored in this canister.
+	///
+	/// # Arguments
+	/// - `state` - Chunks store
+	/// - `http_asset` - Asset served over HTTP
+	///
+	/// # Returns
+	/// - `Option<(Vec<(usize, usize)>, usize)>` - Start and exclusive end of every chunk and length of the asset
+	fn chunk_ranges(state: &ChunksStore, http_asset: &HttpAsset) -> Option<(Vec<(usize, usize)>, usize)> {
+		let mut chunk_ranges: Vec<(usize, usize)> = vec![];
+		let mut length: usize = 0;
+
+		for chunk in &http_asset.chunks {
+			let chunk_length = ChunkBlobs::len(chunk.id, state.canister_owner)?;
+
+			chunk_ranges.push((length, length + chunk_length));
+			length += chunk_length;
+		}
+
+		Some((chunk_ranges, length))
+	}
+
+	/// Create the headers of a response of an asset. The same headers are certified and returned.
+	///
+	/// # Arguments
+	/// - `http_asset` - Asset served over HTTP
+	/// - `content_range` - Content range of a partial response
+	///
+	/// # Returns
+	/// - `Vec<HeaderField>` - Headers
+	fn http_asset_headers(http_asset: &HttpAsset, content_range: Option<String>) -> Vec<HeaderField> {
+		let mut headers = vec![
+			("Content-Type".to_string(), http_asset.mime_type.clone()),
+			("Accept-Ranges".to_string(), "bytes".to_string()),
+			(CERTIFICATE_EXPRESSION_HEADER.to_string(), CERTIFICATE_EXPRESSION.to_string())
+		];
+
+		if let Some(content_range) = content_range {
+			headers.push(("Content-Range".to_string(), content_range));
+		}
+
+		headers
+	}
+
+	/// Create the `Content-Range` header of a partial response.
+	///
+	/// # Arguments
+	/// - `start` - Start of the range
+	/// - `end` - Exclusive end of the range
+	/// - `length` - Length of the asset
+	///
+	/// # Returns
+	/// - `String` - Content range, e.g. `bytes 0-499/1000`
+	fn content_range(start: usize, end: usize, length: usize) -> String {
+		format!("bytes {}-{}/{}", start, end - 1, length)
+	}
+
+	/// Get the hash of an empty body, as HEAD responses have one.
+	///
+	/// # Returns
+	/// - `Hash` - SHA-256 hash
+	fn empty_body_hash() -> Hash {
+		Sha256::digest([]).into()
+	}
+
+	/// Create the `IC-Certificate` header of a response. It contains the certificate of the canister and a
+	/// witness of the response in the certified tree.
+	///
+	/// # Arguments
+	/// - `asset_id` - Asset ID
+	/// - `response_hash` - Hash of the response
+	///
+	/// # Returns
+	/// - `Option<HeaderField>` - Header, if a certificate is available
+	fn certificate_header(asset_id: u32, response_hash: &Hash) -> Option<HeaderField> {
+		let certificate = data_certificate()?;
+
+		CERTIFIED_RESPONSES.with(|certified_responses| {
+			let certified_responses = certified_responses.borrow();
+			let path = asset_id.to_string();
+
+			let tree = labeled(
+				LABEL_HTTP_EXPR.as_bytes(),
+				certified_responses.nested_witness(path.as_bytes(), |asset_responses| asset_responses.witness(response_hash))
+			);
+			let expr_path = [LABEL_HTTP_EXPR, path.as_str(), EXACT_PATH_LABEL];
+
+			Some((
+				"IC-Certificate".to_string(),
+				format!(
+					"certificate=:{}:, tree=:{}:, expr_path=:{}:, version=2",
+					base64::encode(certificate),
+					base64::encode(Self::encode_cbor(&tree)?),
+					base64::encode(Self::encode_cbor(&expr_path)?)
+				),
+			))
+		})
+	}
+
+	/// Encode a value as self-describing CBOR.
+	///
+	/// # Arguments
+	/// - `value` - Value to encode
+	///
+	/// # Returns
+	/// - `Option<Vec<u8>>` - Encoded value
+	fn encode_cbor<T: Serialize>(value: &T) -> Option<Vec<u8>> {
+		let mut serializer = serde_cbor::ser::Serializer::new(vec![]);
+		serializer.self_describe().ok()?;
+		value.serialize(&mut serializer).ok()?;
+
+		Some(serializer.into_inner())
+	}
+
+	/// Find the byte range of a `Range` header. Only a single range is supported, other ranges are ignored
+	/// and the full asset is returned.
+	///
+	/// # Arguments
+	/// - `headers` - Headers of the HTTP request
+	///
+	/// # Returns
+	/// - `Option<&str>` - Byte range, e.g. `0-499`
+	fn find_range(headers: &[HeaderField]) -> Option<&str> {
+		headers
+			.iter()
+			.find(|(name, _)| name.eq_ignore_ascii_case("range"))
+			.and_then(|(_, value)| value.trim().strip_prefix("bytes="))
+			.filter(|range| !range.contains(','))
+	}
+
+	/// Parse a byte range into the first and last byte position, both inclusive.
+	///
+	/// # Arguments
+	/// - `range` - Byte range, e.g. `0-499`, `500-` or `-500`
+	/// - `length` - Length of the asset in bytes
+	///
+	/// # Returns
+	/// - `Option<(usize, usize)>` - First and last byte position, `None` if the range can't be satisfied
+	fn parse_range(range: &str, length: usize) -> Option<(usize, usize)> {
+		if length == 0 {
+			return None;
+		}
+
+		let (start, end) = range.split_once('-')?;
+
+		let (start, end) = match (start.trim(), end.trim()) {
+			// Last bytes of the asset
+			("", suffix_length) => {
+				let suffix_length = suffix_length.parse::<usize>().ok().filter(|suffix_length| *suffix_length > 0)?;
+				(length.saturating_sub(suffix_length), length - 1)
+			}
+			// All bytes from the start position
+			(start, "") => (start.parse::<usize>().ok()?, length - 1),
+			(start, end) => (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?.min(length - 1)),
+		};
+
+		if start > end || start >= length {
+			return None;
+		}
+
+		Some((start, end))
+	}
+
+	/// Get the token of the chunk after the given chunk index.
+	///
+	/// # Arguments
+	/// - `http_asset` - Asset that is streamed
+	/// - `index` - Index of the current chunk
+	///
+	/// # Returns
+	/// - `Option<StreamingCallbackToken>` - Token of the next chunk, if any
+	fn next_streaming_token(http_asset: &HttpAsset, index: u32) -> Option<StreamingCallbackToken> {
+		let next_index = index + 1;
+
+		if (next_index as usize) < http_asset.chunks.len() {
+			Some(StreamingCallbackToken {
+				asset_id: http_asset.asset_id,
+				index: next_index,
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Create an HTTP error response.
+	///
+	/// # Arguments
+	/// - `status_code` - HTTP status code
+	/// - `message` - Error message
+	///
+	/// # Returns
+	/// - `HttpResponse` - HTTP response
+	fn http_error(status_code: u16, message: &str) -> HttpResponse {
+		HttpResponse {
+			status_code,
+			headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+			body: message.as_bytes().to_vec(),
+			streaming_strategy: None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_range() {
+		assert_eq!(ChunksStore::parse_range("0-499", 1000), Some((0, 499)));
+		assert_eq!(ChunksStore::parse_range("500-", 1000), Some((500, 999)));
+		assert_eq!(ChunksStore::parse_range("-500", 1000), Some((500, 999)));
+		assert_eq!(ChunksStore::parse_range(" 10 - 20 ", 1000), Some((10, 20)));
+		// The end is limited to the last byte, a longer suffix is the whole asset
+		assert_eq!(ChunksStore::parse_range("900-2000", 1000), Some((900, 999)));
+		assert_eq!(ChunksStore::parse_range("-2000", 1000), Some((0, 999)));
+	}
+
+	#[test]
+	fn parse_unsatisfiable_range() {
+		assert_eq!(ChunksStore::parse_range("0-0", 0), None);
+		assert_eq!(ChunksStore::parse_range("1000-", 1000), None);
+		assert_eq!(ChunksStore::parse_range("20-10", 1000), None);
+		assert_eq!(ChunksStore::parse_range("-0", 1000), None);
+		assert_eq!(ChunksStore::parse_range("-", 1000), None);
+		assert_eq!(ChunksStore::parse_range("a-b", 1000), None);
+		assert_eq!(ChunksStore::parse_range("100", 1000), None);
+	}
 }
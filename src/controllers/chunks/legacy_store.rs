@@ -0,0 +1,58 @@
+use crate::{ chunk_blobs::ChunkBlobs, chunks_store::ChunksStore };
+use candid::{ CandidType, Deserialize, Principal };
+use std::collections::HashMap;
+
+/// Layout of the `ChunksStore` as deployed before assets were served over HTTP. Only used to migrate the
+/// stable memory of deployed canisters in `post_upgrade`.
+#[derive(CandidType, Clone, Deserialize)]
+pub struct LegacyChunksStore {
+	pub canister_owner: Principal,
+	pub chunk_id: u32,
+	pub chunks: HashMap<(u32, Principal), Vec<u8>>,
+}
+
+impl From<LegacyChunksStore> for ChunksStore {
+	/// Moves the blobs to stable memory. This must only be called after the legacy store is restored, as the
+	/// memory manager overwrites the serialized store.
+	fn from(legacy_store: LegacyChunksStore) -> Self {
+		for ((chunk_id, principal), blob) in legacy_store.chunks {
+			ChunkBlobs::insert(chunk_id, principal, &blob);
+		}
+
+		Self {
+			canister_owner: legacy_store.canister_owner,
+			chunk_id: legacy_store.chunk_id,
+			http_assets: HashMap::new(),
+			http_asset_hashes: HashMap::new(),
+			upload_session_id: 0,
+			upload_sessions: HashMap::new(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn legacy_store_blobs_are_moved_to_stable_memory() {
+		let owner = Principal::from_slice(&[1]);
+		let large_blob = vec![7; 40 * 1024];
+
+		let store = ChunksStore::from(LegacyChunksStore {
+			canister_owner: owner,
+			chunk_id: 2,
+			chunks: HashMap::from([((1, owner), vec![1, 2, 3]), ((2, owner), large_blob.clone())]),
+		});
+
+		assert_eq!(store.chunk_id, 2);
+		assert!(store.http_assets.is_empty());
+		assert_eq!(ChunkBlobs::get(1, owner), Some(vec![1, 2, 3]));
+		assert_eq!(ChunkBlobs::get(2, owner), Some(large_blob.clone()));
+		assert_eq!(ChunkBlobs::len(2, owner), Some(large_blob.len()));
+
+		let mut keys = ChunkBlobs::keys();
+		keys.sort();
+		assert_eq!(keys, vec![(1, owner), (2, owner)]);
+	}
+}
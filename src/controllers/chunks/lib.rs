@@ -1,2 +1,5 @@
+pub mod certified_responses;
+pub mod chunk_blobs;
 pub mod chunks_controller;
 pub mod chunks_store;
+pub mod legacy_store;
@@ -0,0 +1,49 @@
+use crate::users_store::UsersStore;
+use candid::{ CandidType, Deserialize, Principal };
+use lib::types::user::User;
+use std::collections::HashMap;
+
+/// Layout of the `UsersStore` as deployed before the users were kept in stable memory. The whole store was
+/// serialized with `storage::stable_save`. Only used to migrate the stable memory of deployed canisters in
+/// `post_upgrade`.
+#[derive(CandidType, Clone, Deserialize)]
+pub struct HeapUsersStore {
+	pub users: HashMap<Principal, User>,
+	pub chunks_wasm: Vec<u8>,
+}
+
+impl From<HeapUsersStore> for UsersStore {
+	/// Moves the users to stable memory. This must only be called after the heap store is restored, as the memory
+	/// manager overwrites the serialized store.
+	fn from(heap_store: HeapUsersStore) -> Self {
+		let mut store = UsersStore {
+			chunks_wasm: heap_store.chunks_wasm,
+			..UsersStore::default()
+		};
+
+		for (principal, user) in heap_store.users {
+			store.users.insert(principal, user);
+		}
+
+		store
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn heap_store_is_moved_to_stable_maps() {
+		let principal = Principal::from_slice(&[1]);
+		let user = User { user_id: principal, username: Some("user".to_string()), ..User::default() };
+
+		let store = UsersStore::from(HeapUsersStore {
+			users: HashMap::from([(principal, user)]),
+			chunks_wasm: vec![1, 2, 3],
+		});
+
+		assert_eq!(store.chunks_wasm, vec![1, 2, 3]);
+		assert_eq!(store.users.get(&principal).and_then(|user| user.username), Some("user".to_string()));
+	}
+}
@@ -1,2 +1,3 @@
+pub mod legacy_store;
 pub mod users_controller;
 pub mod users_store;
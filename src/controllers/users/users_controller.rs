@@ -1,18 +1,33 @@
 use std::collections::HashMap;
-use crate::users_store::{ UsersStore, STATE };
+use crate::{ legacy_store::HeapUsersStore, users_store::{ UsersStore, STATE } };
 use candid::{ candid_method, Principal };
 use ic_cdk::{ caller, storage };
 use ic_cdk_macros::{ post_upgrade, pre_upgrade, query, update };
-use lib::{ types::{ api_error::ApiError, user::User }, utils::{ validate_anonymous, validate_admin } };
+use lib::{
+	stable_memory,
+	types::{ api_error::ApiError, user::{ User, UserStoreState } },
+	utils::{ validate_anonymous, validate_admin },
+};
 
 #[pre_upgrade]
 fn pre_upgrade() {
-	STATE.with(|state| storage::stable_save((state,)).unwrap());
+	// The users are already in stable memory, only the WASM of the chunks canister is saved
+	STATE.with(|state| stable_memory::save_state(&state.borrow().chunks_wasm));
 }
 
 #[post_upgrade]
 fn post_upgrade() {
-	let (old_store,): (UsersStore,) = storage::stable_restore().unwrap();
+	let old_store = if stable_memory::is_memory_managed() {
+		UsersStore {
+			chunks_wasm: stable_memory::restore_state().unwrap(),
+			..UsersStore::default()
+		}
+	} else {
+		// Canisters deployed before the users were kept in stable memory hold the whole store
+		let (heap_store,): (HeapUsersStore,) = storage::stable_restore().unwrap();
+		UsersStore::from(heap_store)
+	};
+
 	STATE.with(|state| {
 		*state.borrow_mut() = old_store;
 	});
@@ -22,9 +37,19 @@ fn post_upgrade() {
 
 #[query]
 #[candid_method(query)]
-fn get_state() -> Result<HashMap<Principal, User>, ApiError> {
+fn get_state() -> Result<UserStoreState, ApiError> {
 	match validate_admin(&caller()) {
-		Ok(_) => Ok(STATE.with(|state| state.borrow().users.clone())),
+		Ok(_) =>
+			Ok(
+				STATE.with(|state| {
+					let state = state.borrow();
+
+					UserStoreState {
+						users: state.users.keys().collect(),
+						chunks_wasm_size: state.chunks_wasm.len() as u64,
+					}
+				})
+			),
 		Err(err) => Err(err),
 	}
 }
@@ -47,6 +72,15 @@ fn get_all_chunk_canisters() -> Result<HashMap<Principal, Vec<Principal>>, ApiEr
 	}
 }
 
+#[query]
+#[candid_method(query)]
+fn get_user_intercanister_call(user_principal: Principal) -> Result<User, ApiError> {
+	match validate_admin(&caller()) {
+		Ok(_) => UsersStore::get_user(user_principal),
+		Err(err) => Err(err),
+	}
+}
+
 #[query]
 #[candid_method(query)]
 fn get_chunks_wasm() -> Vec<u8> {
@@ -1,17 +1,32 @@
-use candid::{ CandidType, Deserialize, Principal };
+use candid::Principal;
 use ic_cdk::{ api::time, caller, id };
+use ic_stable_structures::memory_manager::MemoryId;
 use lib::{
 	types::{ api_error::{ ApiError, CanisterFailedError }, user::User },
 	canister::{ Canister, CanisterSettings, InstallCodeMode, CanisterID },
+	stable_memory::StableMap,
 };
 use std::{ cell::RefCell, collections::HashMap };
 
-#[derive(CandidType, Clone, Deserialize, Default)]
+// Memory the users are kept in. Memory 0 holds the heap state saved on upgrades
+const USERS_MEMORY_ID: MemoryId = MemoryId::new(1);
+
 pub struct UsersStore {
-	pub users: HashMap<Principal, User>,
+	// All users, kept in stable memory, so upgrades don't depend on the number of users. Principal = user
+	pub users: StableMap<Principal, User>,
+	// Kept on the heap and saved on upgrades
 	pub chunks_wasm: Vec<u8>,
 }
 
+impl Default for UsersStore {
+	fn default() -> Self {
+		Self {
+			users: StableMap::init(USERS_MEMORY_ID),
+			chunks_wasm: vec![],
+		}
+	}
+}
+
 thread_local! {
 	pub static STATE: RefCell<UsersStore> = RefCell::new(UsersStore::default());
 }
@@ -24,7 +39,7 @@ impl UsersStore {
 	/// # Returns
 	/// - `Vec<User>` - Users
 	pub fn get_all_users() -> Vec<User> {
-		STATE.with(|state| state.borrow().users.values().cloned().collect())
+		STATE.with(|state| state.borrow().users.values().collect())
 	}
 
 	/// Get all chunk canisters.
@@ -37,7 +52,7 @@ impl UsersStore {
 			let mut result = HashMap::new();
 
 			for (principal, user) in state.users.iter() {
-				result.insert(principal.clone(), user.canisters.clone());
+				result.insert(principal, user.canisters);
 			}
 
 			result
@@ -57,8 +72,7 @@ impl UsersStore {
 		STATE.with(|state| {
 			let state = state.borrow();
 
-			let opt_user = state.users.get(&caller_principal);
-			opt_user.map_or(Err(ApiError::NotFound("USER_NOT_FOUND".to_string())), |user| Ok(user.clone()))
+			state.users.get(&caller_principal).ok_or(ApiError::NotFound("USER_NOT_FOUND".to_string()))
 		})
 	}
 
@@ -104,8 +118,9 @@ impl UsersStore {
 						STATE.with(|state| {
 							let mut state = state.borrow_mut();
 
-							if let Some(user) = state.users.get_mut(&user.user_id) {
-								user.canisters.push(canister_principal);
+							if let Some(mut stored_user) = state.users.get(&user.user_id) {
+								stored_user.canisters.push(canister_principal);
+								state.users.insert(user.user_id, stored_user);
 							}
 
 							// Return the created user
@@ -6,9 +6,12 @@ pub mod types {
 	pub mod settings;
 	pub mod invite;
 	pub mod nft;
+	pub mod http;
+	pub mod tag;
 }
 
 pub mod save_candid;
 pub mod utils;
 pub mod whitelist;
 pub mod canister;
+pub mod stable_memory;
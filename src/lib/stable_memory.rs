@@ -0,0 +1,579 @@
+use candid::{ decode_one, encode_one, CandidType, Deserialize, Principal };
+use ic_stable_structures::{
+	btreemap,
+	memory_manager::{ MemoryId, MemoryManager, VirtualMemory },
+	storable::Blob,
+	DefaultMemoryImpl,
+	Memory as _,
+	StableBTreeMap,
+};
+use std::{ cell::RefCell, iter::Peekable, marker::PhantomData };
+
+pub type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+// Memory the heap state of a canister is saved to on upgrades. The other IDs are up to the canisters
+pub const UPGRADES_MEMORY_ID: MemoryId = MemoryId::new(0);
+
+const WASM_PAGE_SIZE: u64 = 65536;
+// Maximum size of an encoded key of a stable map in bytes
+const MAX_KEY_SIZE: usize = 256;
+// Values of a stable map have a maximum size, so values are split into pages of this size
+const VALUE_PAGE_SIZE: usize = 1024;
+// Magic bytes the memory manager writes at the start of the stable memory
+const MEMORY_MANAGER_MAGIC: &[u8; 3] = b"MGR";
+
+thread_local! {
+	static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> = RefCell::new(
+		MemoryManager::init(DefaultMemoryImpl::default())
+	);
+}
+
+/// Get a virtual memory of the stable memory.
+///
+/// # Arguments
+/// - `memory_id` - Memory ID
+///
+/// # Returns
+/// - `Memory` - Virtual memory
+pub fn get_memory(memory_id: MemoryId) -> Memory {
+	MEMORY_MANAGER.with(|memory_manager| memory_manager.borrow().get(memory_id))
+}
+
+/// Check if the stable memory is managed by the memory manager. Canisters deployed before stable structures
+/// were used hold the whole state serialized with `storage::stable_save` instead, which has to be restored before
+/// the memory manager is used for the first time.
+///
+/// # Returns
+/// - `bool` - True if the stable memory is managed by the memory manager
+pub fn is_memory_managed() -> bool {
+	let memory = DefaultMemoryImpl::default();
+
+	if memory.size() == 0 {
+		return false;
+	}
+
+	let mut magic = [0; 3];
+	memory.read(0, &mut magic);
+
+	&magic == MEMORY_MANAGER_MAGIC
+}
+
+/// Save the heap state of a canister to the upgrades memory.
+///
+/// # Arguments
+/// - `state` - State to save
+///
+/// # Returns
+/// - `()` - No return value
+pub fn save_state<T: CandidType>(state: &T) {
+	let bytes = encode_one(state).expect("Failed to encode the state");
+	let memory = get_memory(UPGRADES_MEMORY_ID);
+
+	// The state is prefixed with its length
+	let size = 8 + bytes.len() as u64;
+	let pages = size.div_ceil(WASM_PAGE_SIZE);
+
+	if memory.size() < pages && memory.grow(pages - memory.size()) == -1 {
+		panic!("Failed to grow the upgrades memory");
+	}
+
+	memory.write(0, &(bytes.len() as u64).to_le_bytes());
+	memory.write(8, &bytes);
+}
+
+/// Restore the heap state of a canister from the upgrades memory.
+///
+/// # Returns
+/// - `T` - Restored state
+pub fn restore_state<T: CandidType + for<'de> Deserialize<'de>>() -> Result<T, String> {
+	let memory = get_memory(UPGRADES_MEMORY_ID);
+
+	if memory.size() == 0 {
+		return Err("NO_STATE_SAVED".to_string());
+	}
+
+	let mut length = [0; 8];
+	memory.read(0, &mut length);
+
+	let mut bytes = vec![0; u64::from_le_bytes(length) as usize];
+	memory.read(8, &mut bytes);
+
+	decode_one(&bytes).map_err(|err| err.to_string())
+}
+
+/// Key of a `StableMap`. Keys are encoded to bytes that sort in the same order as the keys and no encoded key is the
+/// start of another encoded key, so the entries of a map can be scanned by the first values of their keys.
+pub trait StableKey: Sized {
+	/// Append the encoded key to the bytes.
+	fn encode(&self, bytes: &mut Vec<u8>);
+
+	/// Decode a key from the start of the bytes and move the bytes past it.
+	fn decode(bytes: &mut &[u8]) -> Self;
+
+	/// Encode the key.
+	fn to_key_bytes(&self) -> Vec<u8> {
+		let mut bytes = vec![];
+		self.encode(&mut bytes);
+
+		bytes
+	}
+}
+
+impl StableKey for () {
+	fn encode(&self, _bytes: &mut Vec<u8>) {}
+
+	fn decode(_bytes: &mut &[u8]) -> Self {}
+}
+
+impl StableKey for u32 {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		bytes.extend_from_slice(&self.to_be_bytes());
+	}
+
+	fn decode(bytes: &mut &[u8]) -> Self {
+		let (value, rest) = bytes.split_at(4);
+		*bytes = rest;
+
+		u32::from_be_bytes(value.try_into().unwrap())
+	}
+}
+
+impl StableKey for u64 {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		bytes.extend_from_slice(&self.to_be_bytes());
+	}
+
+	fn decode(bytes: &mut &[u8]) -> Self {
+		let (value, rest) = bytes.split_at(8);
+		*bytes = rest;
+
+		u64::from_be_bytes(value.try_into().unwrap())
+	}
+}
+
+impl StableKey for String {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		encode_bytes(self.as_bytes(), bytes);
+	}
+
+	fn decode(bytes: &mut &[u8]) -> Self {
+		String::from_utf8(decode_bytes(bytes)).unwrap()
+	}
+}
+
+impl StableKey for Principal {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		encode_bytes(self.as_slice(), bytes);
+	}
+
+	fn decode(bytes: &mut &[u8]) -> Self {
+		Principal::from_slice(&decode_bytes(bytes))
+	}
+}
+
+impl<T: StableKey> StableKey for Option<T> {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		match self {
+			Some(value) => {
+				bytes.push(1);
+				value.encode(bytes);
+			}
+			None => bytes.push(0),
+		}
+	}
+
+	fn decode(bytes: &mut &[u8]) -> Self {
+		let (tag, rest) = bytes.split_at(1);
+		*bytes = rest;
+
+		match tag[0] {
+			0 => None,
+			_ => Some(T::decode(bytes)),
+		}
+	}
+}
+
+impl<A: StableKey, B: StableKey> StableKey for (A, B) {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		self.0.encode(bytes);
+		self.1.encode(bytes);
+	}
+
+	fn decode(bytes: &mut &[u8]) -> Self {
+		(A::decode(bytes), B::decode(bytes))
+	}
+}
+
+impl<A: StableKey, B: StableKey, C: StableKey> StableKey for (A, B, C) {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		self.0.encode(bytes);
+		self.1.encode(bytes);
+		self.2.encode(bytes);
+	}
+
+	fn decode(bytes: &mut &[u8]) -> Self {
+		(A::decode(bytes), B::decode(bytes), C::decode(bytes))
+	}
+}
+
+/// Append bytes of variable length to an encoded key. Zero bytes are escaped as `0x00 0xff` and the end is marked
+/// with `0x00 0x01`, which keeps the order of the bytes. A zero byte is always followed by one of the two, so the
+/// encoded bytes are never the start of other encoded bytes.
+///
+/// # Arguments
+/// - `value` - Bytes to append
+/// - `bytes` - Encoded key
+///
+/// # Returns
+/// - `()` - No return value
+fn encode_bytes(value: &[u8], bytes: &mut Vec<u8>) {
+	for byte in value {
+		bytes.push(*byte);
+
+		if *byte == 0 {
+			bytes.push(0xff);
+		}
+	}
+
+	bytes.extend_from_slice(&[0, 1]);
+}
+
+/// Decode bytes of variable length from the start of an encoded key.
+///
+/// # Arguments
+/// - `bytes` - Encoded key, moved past the decoded bytes
+///
+/// # Returns
+/// - `Vec<u8>` - Decoded bytes
+fn decode_bytes(bytes: &mut &[u8]) -> Vec<u8> {
+	let mut value = vec![];
+	let mut position = 0;
+
+	while position < bytes.len() {
+		let byte = bytes[position];
+		position += 1;
+
+		if byte == 0 {
+			// An escaped zero byte is followed by 0xff, the end by 0x01
+			let is_end = bytes.get(position) != Some(&0xff);
+			position += 1;
+
+			if is_end {
+				break;
+			}
+		}
+
+		value.push(byte);
+	}
+
+	*bytes = &bytes[position..];
+
+	value
+}
+
+type PageKey = Blob<{ MAX_KEY_SIZE + 4 }>;
+type ValuePage = Blob<VALUE_PAGE_SIZE>;
+
+/// Map that keeps its entries in stable memory, so it doesn't have to be serialized on upgrades. Values are encoded
+/// with Candid and stored in pages, the keys of the pages are the encoded key followed by the page number.
+pub struct StableMap<K, V> {
+	pages: StableBTreeMap<PageKey, ValuePage, Memory>,
+	marker: PhantomData<(K, V)>,
+}
+
+impl<K: StableKey, V: CandidType + for<'de> Deserialize<'de>> StableMap<K, V> {
+	/// Load the map from a virtual memory, or create it if the memory is empty.
+	///
+	/// # Arguments
+	/// - `memory_id` - Memory ID the map is stored in
+	///
+	/// # Returns
+	/// - `StableMap` - Map
+	pub fn init(memory_id: MemoryId) -> Self {
+		Self {
+			pages: StableBTreeMap::init(get_memory(memory_id)),
+			marker: PhantomData,
+		}
+	}
+
+	/// Get a value.
+	///
+	/// # Arguments
+	/// - `key` - Key
+	///
+	/// # Returns
+	/// - `Option<V>` - Value
+	pub fn get(&self, key: &K) -> Option<V> {
+		self.entries(key.to_key_bytes()).next().map(|(_, value)| value)
+	}
+
+	/// Check if the map contains a key.
+	///
+	/// # Arguments
+	/// - `key` - Key
+	///
+	/// # Returns
+	/// - `bool` - True if the map contains the key
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.pages.contains_key(&Self::page_key(&key.to_key_bytes(), 0))
+	}
+
+	/// Insert a value. An existing value of the key is replaced.
+	///
+	/// # Arguments
+	/// - `key` - Key
+	/// - `value` - Value
+	///
+	/// # Returns
+	/// - `()` - No return value
+	pub fn insert(&mut self, key: K, value: V) {
+		let key_bytes = key.to_key_bytes();
+		let bytes = encode_one(value).expect("Failed to encode the value");
+
+		self.remove_pages(&key_bytes);
+
+		for (page, page_bytes) in bytes.chunks(VALUE_PAGE_SIZE).enumerate() {
+			let value_page = ValuePage::try_from(page_bytes).expect("Page exceeds the page size");
+			self.pages.insert(Self::page_key(&key_bytes, page as u32), value_page);
+		}
+	}
+
+	/// Remove a value.
+	///
+	/// # Arguments
+	/// - `key` - Key
+	///
+	/// # Returns
+	/// - `Option<V>` - Removed value
+	pub fn remove(&mut self, key: &K) -> Option<V> {
+		let value = self.get(key);
+		self.remove_pages(&key.to_key_bytes());
+
+		value
+	}
+
+	/// Get all entries, ordered by key.
+	///
+	/// # Returns
+	/// - `Entries<K, V>` - Entries
+	pub fn iter(&self) -> Entries<'_, K, V> {
+		self.entries(vec![])
+	}
+
+	/// Get the values of all entries, ordered by key.
+	///
+	/// # Returns
+	/// - `impl Iterator<Item = V>` - Values
+	pub fn values(&self) -> impl Iterator<Item = V> + '_ {
+		self.iter().map(|(_, value)| value)
+	}
+
+	/// Get all keys, ordered by key. Values are not read.
+	///
+	/// # Returns
+	/// - `impl Iterator<Item = K>` - Keys
+	pub fn keys(&self) -> impl Iterator<Item = K> + '_ {
+		self.prefix_keys(&())
+	}
+
+	/// Get the entries whose keys start with the given values, ordered by key. For a key `(a, b)` the prefix can
+	/// be `a` or `(a, b)`.
+	///
+	/// # Arguments
+	/// - `prefix` - First values of the keys
+	///
+	/// # Returns
+	/// - `Entries<K, V>` - Entries
+	pub fn prefix<P: StableKey>(&self, prefix: &P) -> Entries<'_, K, V> {
+		self.entries(prefix.to_key_bytes())
+	}
+
+	/// Get the keys whose keys start with the given values, ordered by key. Values are not read.
+	///
+	/// # Arguments
+	/// - `prefix` - First values of the keys
+	///
+	/// # Returns
+	/// - `impl Iterator<Item = K>` - Keys
+	pub fn prefix_keys<P: StableKey>(&self, prefix: &P) -> impl Iterator<Item = K> + '_ {
+		let prefix = prefix.to_key_bytes();
+
+		self.pages
+			.range(Self::page_key(&prefix, 0)..)
+			.take_while(move |(page_key, _)| page_key.as_slice().starts_with(&prefix))
+			.filter_map(|(page_key, _)| {
+				let (key_bytes, page) = page_key.as_slice().split_at(page_key.len() - 4);
+				(page == [0; 4]).then(|| K::decode(&mut &key_bytes[..]))
+			})
+	}
+
+	/// Get the entries from a key on, ordered by key.
+	///
+	/// # Arguments
+	/// - `start` - Key of the first entry
+	///
+	/// # Returns
+	/// - `Entries<K, V>` - Entries
+	pub fn range_from(&self, start: &K) -> Entries<'_, K, V> {
+		Entries {
+			pages: self.pages.range(Self::page_key(&start.to_key_bytes(), 0)..).peekable(),
+			prefix: vec![],
+			marker: PhantomData,
+		}
+	}
+
+	/// Get the entries whose encoded keys start with the given bytes.
+	///
+	/// # Arguments
+	/// - `prefix` - Start of the encoded keys
+	///
+	/// # Returns
+	/// - `Entries<K, V>` - Entries
+	fn entries(&self, prefix: Vec<u8>) -> Entries<'_, K, V> {
+		Entries {
+			pages: self.pages.range(Self::page_key(&prefix, 0)..).peekable(),
+			prefix,
+			marker: PhantomData,
+		}
+	}
+
+	/// Remove all pages of a value.
+	///
+	/// # Arguments
+	/// - `key_bytes` - Encoded key
+	///
+	/// # Returns
+	/// - `()` - No return value
+	fn remove_pages(&mut self, key_bytes: &[u8]) {
+		let page_keys: Vec<PageKey> = self.pages
+			.range(Self::page_key(key_bytes, 0)..=Self::page_key(key_bytes, u32::MAX))
+			.map(|(page_key, _)| page_key)
+			.collect();
+
+		for page_key in &page_keys {
+			self.pages.remove(page_key);
+		}
+	}
+
+	/// Create the key of a page of a value.
+	///
+	/// # Arguments
+	/// - `key_bytes` - Encoded key
+	/// - `page` - Page number
+	///
+	/// # Returns
+	/// - `PageKey` - Key of the page
+	fn page_key(key_bytes: &[u8], page: u32) -> PageKey {
+		let mut bytes = key_bytes.to_vec();
+		bytes.extend_from_slice(&page.to_be_bytes());
+
+		PageKey::try_from(bytes.as_slice()).expect("Key exceeds the maximum key size")
+	}
+}
+
+/// Iterator over the entries of a `StableMap`. The pages of each value are joined and decoded.
+pub struct Entries<'a, K, V> {
+	pages: Peekable<btreemap::Iter<'a, PageKey, ValuePage, Memory>>,
+	// Start of the encoded keys of the entries, the iterator ends at the first key that doesn't match
+	prefix: Vec<u8>,
+	marker: PhantomData<(K, V)>,
+}
+
+impl<K: StableKey, V: CandidType + for<'de> Deserialize<'de>> Iterator for Entries<'_, K, V> {
+	type Item = (K, V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (page_key, value_page) = self.pages.next()?;
+
+		if !page_key.as_slice().starts_with(&self.prefix) {
+			return None;
+		}
+
+		let key_bytes = page_key.as_slice()[..page_key.len() - 4].to_vec();
+		let mut bytes = value_page.as_slice().to_vec();
+
+		// The following pages of the same key belong to the value
+		while let Some((next_page_key, _)) = self.pages.peek() {
+			if next_page_key.as_slice()[..next_page_key.len() - 4] != key_bytes[..] {
+				break;
+			}
+
+			if let Some((_, next_value_page)) = self.pages.next() {
+				bytes.extend_from_slice(next_value_page.as_slice());
+			}
+		}
+
+		let key = K::decode(&mut &key_bytes[..]);
+		let value = decode_one(&bytes).expect("Failed to decode the value");
+
+		Some((key, value))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn decode<K: StableKey>(bytes: &[u8]) -> K {
+		K::decode(&mut &bytes[..])
+	}
+
+	#[test]
+	fn keys_round_trip() {
+		let principal = Principal::from_text("2vxsx-fae").unwrap();
+		let key = ("a\0b".to_string(), Some(principal), u64::MAX);
+
+		assert!(decode::<(String, Option<Principal>, u64)>(&key.to_key_bytes()) == key);
+		assert_eq!(decode::<(Option<u32>, String)>(&(None::<u32>, String::new()).to_key_bytes()), (None, String::new()));
+	}
+
+	#[test]
+	fn encoded_keys_keep_the_order() {
+		let keys: Vec<(String, u32)> = vec![
+			(String::new(), u32::MAX),
+			("a".to_string(), 0),
+			("a".to_string(), 1),
+			("a\0".to_string(), 0),
+			("a\0\0".to_string(), 0),
+			("a\u{1}".to_string(), 0),
+			("ab".to_string(), 0),
+			("b".to_string(), 0)
+		];
+
+		for pair in keys.windows(2) {
+			assert!(pair[0] < pair[1]);
+			assert!(pair[0].to_key_bytes() < pair[1].to_key_bytes(), "{:?} < {:?}", pair[0], pair[1]);
+		}
+	}
+
+	#[test]
+	fn encoded_strings_are_not_prefixes() {
+		let strings = ["", "a", "a\0", "a\u{1}", "ab"];
+
+		for a in strings {
+			for b in strings.iter().filter(|b| **b != a) {
+				assert!(!b.to_string().to_key_bytes().starts_with(&a.to_string().to_key_bytes()), "{:?} {:?}", a, b);
+			}
+		}
+	}
+
+	#[test]
+	fn map_entries_by_prefix() {
+		let mut map: StableMap<(String, u32), String> = StableMap::init(MemoryId::new(1));
+
+		map.insert(("ab".to_string(), 1), "ab".to_string());
+		map.insert(("a".to_string(), 2), "a2".to_string());
+		map.insert(("a".to_string(), 1), "x".repeat(3 * VALUE_PAGE_SIZE));
+		map.insert(("a\0".to_string(), 1), "a0".to_string());
+
+		let values: Vec<String> = map.prefix(&"a".to_string()).map(|(_, value)| value).collect();
+		assert_eq!(values, vec!["x".repeat(3 * VALUE_PAGE_SIZE), "a2".to_string()]);
+
+		let keys: Vec<(String, u32)> = map.prefix_keys(&"a".to_string()).collect();
+		assert_eq!(keys, vec![("a".to_string(), 1), ("a".to_string(), 2)]);
+
+		assert_eq!(map.remove(&("a".to_string(), 1)).map(|value| value.len()), Some(3 * VALUE_PAGE_SIZE));
+		assert_eq!(map.keys().count(), 3);
+		assert!(!map.contains_key(&("a".to_string(), 1)));
+	}
+}
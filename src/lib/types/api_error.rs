@@ -7,6 +7,7 @@ pub enum ApiError {
 	Unauthorized(String),
 	NotFound(String),
 	AlreadyExists(String),
+	BadRequest(String),
 	CanisterFailed(CanisterFailedError),
 }
 
@@ -14,8 +14,16 @@ pub struct Asset {
 	pub mime_type: String,
 	pub created_at: u64,
 	pub updated_at: u64,
+	pub deleted_at: Option<u64>,
+	pub tags: Option<Vec<String>>,
 	pub chunks: Vec<Chunk>,
 	pub settings: Settings,
+	// Hash of the file, computed from the blobs stored in the chunks canisters: the SHA-256 hash of the
+	// concatenated SHA-256 hashes of the chunks in the order of their index. Clients compare a download against
+	// this hash by hashing every chunk, not the whole content. The SHA-256 hash of the whole content is the one
+	// certified when a public file is served over HTTP. None for folders, NFTs, files uploaded without a declared
+	// hash and files whose chunks canister couldn't return the hashes of the chunks
+	pub hash: Option<Vec<u8>>,
 }
 
 impl Default for Asset {
@@ -32,8 +40,11 @@ impl Default for Asset {
 			mime_type: Default::default(),
 			created_at: Default::default(),
 			updated_at: Default::default(),
+			deleted_at: None,
+			tags: None,
 			chunks: Default::default(),
 			settings: Default::default(),
+			hash: None,
 		}
 	}
 }
@@ -50,6 +61,10 @@ pub struct PostAsset {
 	pub mime_type: String,
 	pub chunks: Vec<Chunk>,
 	pub settings: Settings,
+	pub on_conflict: Option<NameConflict>,
+	// Expected hash of the file in the format of `Asset::hash`, the asset is rejected if it doesn't match the
+	// hashes of the stored chunks. Files are only verified and hashed if a hash is declared
+	pub hash: Option<Vec<u8>>,
 }
 
 #[derive(CandidType, Clone, Deserialize)]
@@ -59,18 +74,104 @@ pub struct EditAsset {
 	pub is_favorite: Option<bool>,
 	pub name: Option<String>,
 	pub extension: Option<String>,
+	pub on_conflict: Option<NameConflict>,
 }
 
 #[derive(CandidType, Clone, Deserialize)]
 pub struct MoveAsset {
 	pub id: u32,
 	pub parent_id: Option<u32>,
+	pub on_conflict: Option<NameConflict>,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct CopyAsset {
+	pub id: u32,
+	pub parent_id: Option<u32>,
+	pub on_conflict: Option<NameConflict>,
+}
+
+/// What to do when an asset with the same name already exists in a folder. Names are compared case-insensitively.
+#[derive(CandidType, Clone, Copy, Deserialize)]
+pub enum NameConflict {
+	// Reject the call with the ID of the existing asset
+	Fail,
+	// Add a number to the name, e.g. "name (1)"
+	Rename,
+	// Move the existing asset to the trash
+	Replace,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct AssetVersion {
+	pub version: u32,
+	pub size: u32,
+	pub chunks: Vec<Chunk>,
+	pub created_at: u64,
+	// Hash of the file in the format of `Asset::hash`
+	pub hash: Option<Vec<u8>>,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct AssetSort {
+	pub sort_by: SortBy,
+	pub order: SortOrder,
+}
+
+#[derive(CandidType, Clone, Copy, Deserialize)]
+pub enum SortBy {
+	Name,
+	Size,
+	CreatedAt,
+	UpdatedAt,
+}
+
+#[derive(CandidType, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub enum SortOrder {
+	Ascending,
+	Descending,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct SearchAssets {
+	pub query: Option<String>,
+	pub mime_type: Option<String>,
+	pub min_size: Option<u32>,
+	pub max_size: Option<u32>,
+	pub updated_after: Option<u64>,
+	pub updated_before: Option<u64>,
+	pub is_favorite: Option<bool>,
+	pub asset_type: Option<AssetType>,
+	pub tag: Option<String>,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct AssetPage {
+	pub assets: Vec<Asset>,
+	pub total: u32,
+	pub next_cursor: Option<u32>,
 }
 
 #[derive(CandidType, Clone, Deserialize)]
 pub struct SharedWith {
 	pub principal: Principal,
 	pub username: Option<String>,
+	pub permission: Permission,
+}
+
+#[derive(CandidType, Clone, Copy, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Permission {
+	Viewer,
+	Editor,
+	CoOwner,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct SharedAsset {
+	pub owner_principal: Principal,
+	pub owner_username: Option<String>,
+	pub permission: Permission,
+	pub asset: Asset,
 }
 
 #[derive(CandidType, Clone, Deserialize, PartialEq, Eq)]
@@ -79,3 +180,11 @@ pub enum AssetType {
 	File,
 	NFT(Nft),
 }
+
+#[derive(CandidType, Clone, Deserialize, Default)]
+pub struct AssetStoreState {
+	pub asset_id: u32,
+	pub invite_id: u32,
+	pub trash_retention_days: Option<u32>,
+	pub users_canister: Option<Principal>,
+}
@@ -1,10 +1,17 @@
 use candid::{ CandidType, Deserialize, Principal };
+use std::collections::BTreeMap;
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
 pub struct Chunk {
 	pub id: u32,
 	pub index: u32,
 	pub canister: Principal,
+	// Principal the blob is stored under, the owner of the chunks canister. None for chunks added before owners
+	// were recorded
+	pub owner: Option<Principal>,
+	// SHA-256 hash of the blob, read from the chunks canister. None for chunks of unverified files and chunks added
+	// before hashes were recorded
+	pub hash: Option<Vec<u8>>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
@@ -13,6 +20,36 @@ pub struct PostChunk {
 	pub index: u32,
 }
 
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct UploadSession {
+	pub id: u32,
+	pub total_size: u64,
+	pub chunk_count: u32,
+	// Chunks received so far. u32 = index
+	pub chunks: BTreeMap<u32, Chunk>,
+	pub received_size: u64,
+	pub expires_at: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct UploadedChunk {
+	pub index: u32,
+	pub chunk_id: u32,
+	pub size: u64,
+	pub hash: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct UploadStatus {
+	pub session_id: u32,
+	pub total_size: u64,
+	pub chunk_count: u32,
+	// Chunks whose blob is stored, ordered by index
+	pub chunks: Vec<UploadedChunk>,
+	pub received_size: u64,
+	pub expires_at: u64,
+}
+
 #[derive(CandidType)]
 pub struct ChunkStoreState {
 	pub canister_owner: Principal,
@@ -0,0 +1,84 @@
+use super::chunk::Chunk;
+use candid::{
+	parser::types::FuncMode,
+	types::{ reference::Func, Function, Serializer, Type },
+	CandidType,
+	Deserialize,
+	Principal,
+};
+
+pub type HeaderField = (String, String);
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct HttpRequest {
+	pub method: String,
+	pub url: String,
+	pub headers: Vec<HeaderField>,
+	pub body: Vec<u8>,
+}
+
+#[derive(Clone, Debug, CandidType)]
+pub struct HttpResponse {
+	pub status_code: u16,
+	pub headers: Vec<HeaderField>,
+	pub body: Vec<u8>,
+	pub streaming_strategy: Option<StreamingStrategy>,
+}
+
+#[derive(Clone, Debug, CandidType)]
+pub enum StreamingStrategy {
+	Callback {
+		callback: StreamingCallback,
+		token: StreamingCallbackToken,
+	},
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct StreamingCallbackToken {
+	pub asset_id: u32,
+	pub index: u32,
+}
+
+#[derive(Clone, Debug, CandidType)]
+pub struct StreamingCallbackHttpResponse {
+	pub body: Vec<u8>,
+	pub token: Option<StreamingCallbackToken>,
+}
+
+/// Reference to the query method that returns the next part of a streamed response.
+/// `candid::Func` is typed as `func () -> ()`, which the HTTP gateway doesn't accept.
+#[derive(Clone, Debug)]
+pub struct StreamingCallback(pub Func);
+
+impl StreamingCallback {
+	pub fn new(principal: Principal, method: &str) -> Self {
+		Self(Func {
+			principal,
+			method: method.to_string(),
+		})
+	}
+}
+
+impl CandidType for StreamingCallback {
+	fn _ty() -> Type {
+		Type::Func(Function {
+			modes: vec![FuncMode::Query],
+			args: vec![StreamingCallbackToken::ty()],
+			rets: vec![StreamingCallbackHttpResponse::ty()],
+		})
+	}
+
+	fn idl_serialize<S>(&self, serializer: S) -> Result<(), S::Error> where S: Serializer {
+		self.0.idl_serialize(serializer)
+	}
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct HttpAsset {
+	pub asset_id: u32,
+	// Principal the chunks are stored under, the owner of the chunks canister
+	pub owner: Principal,
+	pub name: String,
+	pub mime_type: String,
+	pub chunks: Vec<Chunk>,
+}
@@ -1,10 +1,14 @@
+use super::asset::Permission;
 use candid::{ CandidType, Deserialize, Principal };
 
 #[derive(CandidType, Clone, Deserialize)]
 pub struct Invite {
+	pub id: u32,
 	pub invited_by_principal: Principal,
 	pub invited_by_username: Option<String>,
+	pub invited_username: Option<String>,
 	pub asset_id: u32,
+	pub permission: Permission,
 	pub status: InviteStatus,
 	pub expires_at: Option<u64>,
 }
@@ -12,9 +16,12 @@ pub struct Invite {
 impl Default for Invite {
 	fn default() -> Self {
 		Self {
+			id: Default::default(),
 			invited_by_principal: Principal::anonymous(),
 			invited_by_username: Default::default(),
+			invited_username: Default::default(),
 			asset_id: Default::default(),
+			permission: Permission::Viewer,
 			status: InviteStatus::Pending,
 			expires_at: Default::default(),
 		}
@@ -22,8 +29,17 @@ impl Default for Invite {
 }
 
 #[derive(CandidType, Clone, Deserialize)]
+pub struct PostInvite {
+	pub asset_id: u32,
+	pub principal: Principal,
+	pub permission: Permission,
+	pub expires_at: Option<u64>,
+}
+
+#[derive(CandidType, Clone, Deserialize, PartialEq, Eq)]
 pub enum InviteStatus {
 	Accepted,
 	Pending,
 	Declined,
+	Cancelled,
 }
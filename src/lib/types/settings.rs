@@ -15,6 +15,13 @@ impl Default for Settings {
 	}
 }
 
+#[derive(CandidType, Clone, Deserialize)]
+pub struct EditSettings {
+	pub id: u32,
+	pub privacy: Option<Privacy>,
+	pub url: Option<String>,
+}
+
 #[derive(CandidType, Clone, Deserialize)]
 pub enum Privacy {
 	Public,
@@ -0,0 +1,14 @@
+use candid::{ CandidType, Deserialize };
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct Tag {
+	pub name: String,
+	pub color: Option<String>,
+	pub count: u32,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct EditTags {
+	pub asset_ids: Vec<u32>,
+	pub tags: Vec<String>,
+}
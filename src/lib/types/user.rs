@@ -20,3 +20,9 @@ impl Default for User {
 		}
 	}
 }
+
+#[derive(CandidType)]
+pub struct UserStoreState {
+	pub users: Vec<Principal>,
+	pub chunks_wasm_size: u64,
+}
@@ -1,5 +1,6 @@
 use candid::Principal;
-use crate::{ types::{ asset::{ Asset, AssetType }, api_error::ApiError }, whitelist::whitelist };
+use sha2::{ Digest, Sha256 };
+use crate::{ types::{ asset::{ Asset, AssetType }, api_error::ApiError, chunk::Chunk }, whitelist::whitelist };
 
 /// Get nested child assets.
 ///
@@ -26,6 +27,37 @@ pub fn get_nested_child_assets(assets: &Vec<Asset>, asset_id: &u32) -> Vec<u32>
 	child_assets
 }
 
+/// Hash the blob of a chunk with SHA-256.
+///
+/// # Arguments
+/// - `blob` - Blob of the chunk
+///
+/// # Returns
+/// - `Vec<u8>` - SHA-256 hash
+pub fn hash_chunk(blob: &[u8]) -> Vec<u8> {
+	Sha256::digest(blob).to_vec()
+}
+
+/// Hash a file from the hashes of its chunks. The hash of a file is the SHA-256 hash of the hashes of its chunks
+/// in the order of their index, so it can be checked without reading the chunks.
+///
+/// # Arguments
+/// - `chunks` - Chunks of the file
+///
+/// # Returns
+/// - `Option<Vec<u8>>` - SHA-256 hash, `None` if a chunk has no hash
+pub fn hash_file(chunks: &[Chunk]) -> Option<Vec<u8>> {
+	let mut sorted_chunks: Vec<&Chunk> = chunks.iter().collect();
+	sorted_chunks.sort_by_key(|chunk| chunk.index);
+
+	let mut hasher = Sha256::new();
+	for chunk in sorted_chunks {
+		hasher.update(chunk.hash.as_ref()?);
+	}
+
+	Some(hasher.finalize().to_vec())
+}
+
 /// Validate anonymous.
 ///
 /// # Arguments